@@ -23,7 +23,7 @@ fn main() {
         match evt {
             Event::Key(Key::Char('q')) => break,
             Event::Mouse(me) => match me {
-                MouseEvent::Press(_, x, y) => {
+                MouseEvent::Press(_, x, y, _) => {
                     write!(stdout, "{}x", termion::cursor::Goto(x, y)).unwrap();
                 }
                 _ => (),
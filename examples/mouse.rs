@@ -2,7 +2,7 @@ extern crate termion;
 
 use std::io::{self, Write};
 use termion::cursor::{self, DetectCursorPos};
-use termion::event::*;
+use termion::event::{Event, Key};
 use termion::input::{MouseTerminal, TermRead};
 use termion::raw::IntoRawMode;
 
@@ -22,22 +22,21 @@ fn main() {
         let evt = c.unwrap();
         match evt {
             Event::Key(Key::Char('q')) => break,
-            Event::Mouse(me) => match me {
-                MouseEvent::Press(_, a, b) | MouseEvent::Release(a, b) | MouseEvent::Hold(a, b) => {
-                    write!(stdout, "{}", cursor::Goto(a, b)).unwrap();
-                    let (x, y) = stdout.cursor_pos().unwrap();
-                    write!(
-                        stdout,
-                        "{}{}Cursor is at: ({},{}){}",
-                        cursor::Goto(5, 5),
-                        termion::clear::UntilNewline,
-                        x,
-                        y,
-                        cursor::Goto(a, b)
-                    )
-                    .unwrap();
-                }
-            },
+            Event::Mouse(me) => {
+                let (a, b) = me.position();
+                write!(stdout, "{}", cursor::Goto(a, b)).unwrap();
+                let (x, y) = stdout.cursor_pos().unwrap();
+                write!(
+                    stdout,
+                    "{}{}Cursor is at: ({},{}){}",
+                    cursor::Goto(5, 5),
+                    termion::clear::UntilNewline,
+                    x,
+                    y,
+                    cursor::Goto(a, b)
+                )
+                .unwrap();
+            }
             _ => {}
         }
 
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use termion::input::TermReadEventsAndRaw;
+
+fuzz_target!(|data: &[u8]| {
+    // Parsing must never panic, and every input byte must be accounted for by exactly one event.
+    let mut raw = Vec::new();
+    for event in data.events_and_raw() {
+        let (_, bytes) = event.expect("reading from a slice cannot fail");
+        raw.extend(bytes);
+    }
+    assert_eq!(raw, data);
+});
@@ -1,7 +1,15 @@
+#[cfg(all(unix, not(target_os = "redox")))]
+use std::fs::File;
 use std::io::{self, Read};
+#[cfg(all(unix, not(target_os = "redox")))]
+use std::os::fd::{AsFd, BorrowedFd};
 use std::sync::mpsc;
 use std::thread;
+#[cfg(all(unix, not(target_os = "redox")))]
+use std::time::Duration;
 
+#[cfg(all(unix, not(target_os = "redox")))]
+use sys::poll::poll_read;
 use sys::tty::get_tty;
 
 /// Construct an asynchronous handle to the TTY standard input, with a delimiter byte.
@@ -9,27 +17,7 @@ use sys::tty::get_tty;
 /// This has the same advantages as async_stdin(), but also allows specifying a delimiter byte. The
 /// reader will stop reading after consuming the delimiter byte.
 pub fn async_stdin_until(delimiter: u8) -> AsyncReader {
-    let (send, recv) = mpsc::channel();
-
-    thread::spawn(move || {
-        for i in get_tty().unwrap().bytes() {
-            match i {
-                Ok(byte) => {
-                    let end_of_stream = &byte == &delimiter;
-                    let send_error = send.send(Ok(byte)).is_err();
-
-                    if end_of_stream || send_error {
-                        return;
-                    }
-                }
-                Err(_) => {
-                    return;
-                }
-            }
-        }
-    });
-
-    AsyncReader { recv: recv }
+    AsyncReader::spawn(|| get_tty().unwrap(), StreamEnd::Delimiter(delimiter))
 }
 
 /// Construct an asynchronous handle to the TTY standard input.
@@ -43,17 +31,7 @@ pub fn async_stdin_until(delimiter: u8) -> AsyncReader {
 /// output from another process, it won't be reflected in the stream returned by this function, as
 /// this represents the TTY device, and not the piped standard input.
 pub fn async_stdin() -> AsyncReader {
-    let (send, recv) = mpsc::channel();
-
-    thread::spawn(move || {
-        for i in get_tty().unwrap().bytes() {
-            if send.send(i).is_err() {
-                return;
-            }
-        }
-    });
-
-    AsyncReader { recv: recv }
+    AsyncReader::spawn(|| get_tty().unwrap(), StreamEnd::Never)
 }
 
 /// An asynchronous reader.
@@ -65,7 +43,54 @@ pub struct AsyncReader {
     recv: mpsc::Receiver<io::Result<u8>>,
 }
 
-// FIXME: Allow constructing an async reader from an arbitrary stream.
+impl AsyncReader {
+    /// Construct an asynchronous handle to an arbitrary stream.
+    ///
+    /// Like `async_stdin`, this fires up another thread reading from `source`, so that reading
+    /// from the handle never blocks. Unlike with `async_stdin`, the first error of `source` is
+    /// passed on and ends the stream, and reads return no bytes once it ended and everything read
+    /// from it was consumed.
+    pub fn new<R: Read + Send + 'static>(source: R) -> AsyncReader {
+        AsyncReader::spawn(move || source, StreamEnd::Error)
+    }
+
+    /// Read the stream opened by `open` on another thread, until `end`.
+    fn spawn<F, R>(open: F, end: StreamEnd) -> AsyncReader
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Read,
+    {
+        let (send, recv) = mpsc::channel();
+
+        thread::spawn(move || {
+            for i in open().bytes() {
+                let end_of_stream = match (&i, &end) {
+                    (&Ok(byte), &StreamEnd::Delimiter(delimiter)) => byte == delimiter,
+                    (&Ok(_), _) => false,
+                    (&Err(_), &StreamEnd::Delimiter(_)) => return,
+                    // Interrupted reads are retried already, so the error would only repeat.
+                    (&Err(_), &StreamEnd::Error) => true,
+                    (&Err(_), &StreamEnd::Never) => false,
+                };
+                if send.send(i).is_err() || end_of_stream {
+                    return;
+                }
+            }
+        });
+
+        AsyncReader { recv }
+    }
+}
+
+/// What ends the stream read by an `AsyncReader`.
+enum StreamEnd {
+    /// The delimiter byte, after passing it on, or an error, which is dropped.
+    Delimiter(u8),
+    /// The first error, after passing it on.
+    Error,
+    /// Nothing, errors are passed on and reading goes on.
+    Never,
+}
 
 impl Read for AsyncReader {
     /// Read from the byte stream.
@@ -95,14 +120,156 @@ impl Read for AsyncReader {
     }
 }
 
+/// Construct a non-blocking handle to the TTY, without a background thread.
+///
+/// Unlike `async_stdin`, nothing is read from the TTY unless the handle is read from, so no input
+/// is lost once it is dropped, for example by a child process started afterwards.
+#[cfg(all(unix, not(target_os = "redox")))]
+pub fn poll_stdin() -> io::Result<PollingReader<File>> {
+    Ok(PollingReader::new(get_tty()?))
+}
+
+/// A non-blocking reader polling a file descriptor.
+///
+/// Reads wait until the source is readable, at most for the reader's timeout, and then read only
+/// what is available. Like `AsyncReader`, reads return no bytes if nothing arrived in time.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use termion::input::TermRead;
+///
+/// let tty = termion::poll_stdin()?.timeout(Duration::from_millis(100));
+/// let mut keys = tty.keys();
+/// loop {
+///     match keys.next() {
+///         Some(key) => println!("{:?}", key?),
+///         None => println!("no key within 100ms"),
+///     }
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(all(unix, not(target_os = "redox")))]
+#[derive(Debug)]
+pub struct PollingReader<R> {
+    source: R,
+    timeout: Duration,
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> PollingReader<R> {
+    /// Read from `source` without waiting.
+    pub fn new(source: R) -> PollingReader<R> {
+        PollingReader {
+            source,
+            timeout: Duration::from_secs(0),
+        }
+    }
+
+    /// Wait up to `timeout` for input on every read.
+    pub fn timeout(mut self, timeout: Duration) -> PollingReader<R> {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get a reference to the source.
+    pub fn get_ref(&self) -> &R {
+        &self.source
+    }
+
+    /// Get the source back.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> Read for PollingReader<R> {
+    /// Read what is available, waiting at most for the reader's timeout.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || !poll_read(self.source.as_fd(), Some(self.timeout))? {
+            return Ok(0);
+        }
+        self.source.read(buf)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: AsFd> AsFd for PollingReader<R> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.source.as_fd()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::io::Read;
 
+    use std::time::Duration;
+
     #[test]
     fn test_async_stdin() {
         let stdin = async_stdin();
         stdin.bytes().next();
     }
+
+    #[test]
+    fn test_async_reader() {
+        let mut reader = AsyncReader::new(&b"abc"[..]);
+        let mut read = Vec::new();
+        let mut buf = [0; 2];
+        for _ in 0..100 {
+            let n = reader.read(&mut buf).unwrap();
+            assert!(n <= 2);
+            read.extend_from_slice(&buf[..n]);
+            if read.len() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(read, b"abc");
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_async_reader_error() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("failed"))
+            }
+        }
+
+        // The error ends the stream, rather than being read again and again.
+        let reader = AsyncReader::new(Failing);
+        assert!(reader.recv.recv().unwrap().is_err());
+        assert!(reader.recv.recv().is_err());
+    }
+
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[test]
+    fn test_polling_reader() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+        use std::time::Instant;
+
+        let (mut writer, source) = UnixStream::pair().unwrap();
+        let mut reader = PollingReader::new(source);
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        writer.write_all(b"abcdef").unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"abcd");
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ef");
+
+        let mut reader = reader.timeout(Duration::from_millis(30));
+        let start = Instant::now();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
 }
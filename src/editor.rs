@@ -0,0 +1,658 @@
+//! Line editing.
+//!
+//! `LineEditor` reads a line from input events, letting the user move around and edit it with the
+//! usual Emacs style keys, recall earlier lines from a history, and complete words.
+//!
+//! | Key                                  | Action                                    |
+//! |--------------------------------------|-------------------------------------------|
+//! | Left, Ctrl-B / Right, Ctrl-F         | Move one character                        |
+//! | Ctrl-Left, Alt-B / Ctrl-Right, Alt-F | Move one word                             |
+//! | Home, Ctrl-A / End, Ctrl-E           | Move to the start or end of the line      |
+//! | Backspace, Ctrl-H / Delete           | Delete before or under the cursor         |
+//! | Ctrl-K / Ctrl-U                      | Kill to the end or start of the line      |
+//! | Ctrl-W, Alt-Backspace / Alt-D        | Kill the word before or after the cursor  |
+//! | Ctrl-Y                               | Yank the last killed text                 |
+//! | Up, Ctrl-P / Down, Ctrl-N            | Recall the previous or next history entry |
+//! | Tab                                  | Complete the word before the cursor       |
+//! | Ctrl-L                               | Clear the screen                          |
+//! | Enter                                | Accept the line                           |
+//! | Ctrl-C, or Ctrl-D on an empty line   | Abort                                     |
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use clear;
+use cursor;
+use event::{Event, Key, KeyCode, KeyEventKind, Modifiers};
+use grapheme::{self, next_boundary, prev_boundary};
+use terminal_size;
+
+/// The number of lines kept by `MemoryHistory::new`.
+pub const DEFAULT_HISTORY_LEN: usize = 1000;
+
+/// A store of previously entered lines.
+pub trait History {
+    /// The number of entries.
+    fn len(&self) -> usize;
+
+    /// Whether there are no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The entry at `index`, counting from the oldest.
+    fn get(&self, index: usize) -> Option<String>;
+
+    /// Add a line that was entered.
+    fn add(&mut self, line: &str);
+}
+
+/// A history kept in memory.
+///
+/// Empty lines and lines repeating the previous entry are not added.
+#[derive(Debug, Clone)]
+pub struct MemoryHistory {
+    entries: VecDeque<String>,
+    max_len: usize,
+}
+
+impl MemoryHistory {
+    /// Create an empty history, keeping the last `DEFAULT_HISTORY_LEN` lines.
+    pub fn new() -> MemoryHistory {
+        MemoryHistory::with_max_len(DEFAULT_HISTORY_LEN)
+    }
+
+    /// Create an empty history, keeping the last `max_len` lines.
+    pub fn with_max_len(max_len: usize) -> MemoryHistory {
+        MemoryHistory {
+            entries: VecDeque::new(),
+            max_len,
+        }
+    }
+}
+
+impl Default for MemoryHistory {
+    fn default() -> MemoryHistory {
+        MemoryHistory::new()
+    }
+}
+
+impl History for MemoryHistory {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&self, index: usize) -> Option<String> {
+        self.entries.get(index).cloned()
+    }
+
+    fn add(&mut self, line: &str) {
+        if line.is_empty() || self.entries.back().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.entries.push_back(line.to_string());
+        while self.entries.len() > self.max_len {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// A function completing the word before the cursor.
+///
+/// It is given the line and the cursor position as a byte index, and returns the byte index where
+/// the word to complete starts, along with the candidates to replace it with.
+pub type Completer = Box<dyn FnMut(&str, usize) -> (usize, Vec<String>)>;
+
+/// A line editor.
+///
+/// The terminal must be in raw mode while reading, see `raw::IntoRawMode`. The editor keeps its
+/// history and the last killed text from one line to the next.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{stdin, stdout, Write};
+/// use termion::editor::LineEditor;
+/// use termion::input::TermRead;
+/// use termion::raw::IntoRawMode;
+///
+/// let mut stdout = stdout().into_raw_mode()?;
+/// let mut events = stdin().events();
+/// let mut editor = LineEditor::new().completer(|line, pos| {
+///     let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+///     let words = ["help", "hello", "quit"];
+///     let candidates = words.iter().filter(|w| w.starts_with(&line[start..pos]));
+///     (start, candidates.map(|w| w.to_string()).collect())
+/// });
+///
+/// while let Some(line) = editor.read_line("> ", &mut events, &mut stdout)? {
+///     write!(stdout, "you said {:?}\r\n", line)?;
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+pub struct LineEditor<H = MemoryHistory> {
+    history: H,
+    completer: Option<Completer>,
+    killed: String,
+}
+
+impl LineEditor<MemoryHistory> {
+    /// Create an editor with an empty `MemoryHistory`.
+    pub fn new() -> LineEditor<MemoryHistory> {
+        LineEditor::with_history(MemoryHistory::new())
+    }
+}
+
+impl Default for LineEditor<MemoryHistory> {
+    fn default() -> LineEditor<MemoryHistory> {
+        LineEditor::new()
+    }
+}
+
+impl<H: History> LineEditor<H> {
+    /// Create an editor using the given history.
+    pub fn with_history(history: H) -> LineEditor<H> {
+        LineEditor {
+            history,
+            completer: None,
+            killed: String::new(),
+        }
+    }
+
+    /// Complete words with the given function when Tab is pressed, see `Completer`.
+    ///
+    /// A single candidate replaces the word. With several candidates, the word is first extended
+    /// to their common prefix, and further presses of Tab cycle through them.
+    pub fn completer<F>(mut self, completer: F) -> LineEditor<H>
+    where
+        F: FnMut(&str, usize) -> (usize, Vec<String>) + 'static,
+    {
+        self.completer = Some(Box::new(completer));
+        self
+    }
+
+    /// The history of entered lines.
+    pub fn history(&self) -> &H {
+        &self.history
+    }
+
+    /// The history of entered lines, mutably.
+    pub fn history_mut(&mut self) -> &mut H {
+        &mut self.history
+    }
+
+    /// Read a line, writing `prompt` before it.
+    ///
+    /// Returns `None` if the user aborts with Ctrl-C or Ctrl-D, or if the events end before
+    /// anything was entered. The accepted line is added to the history.
+    ///
+    /// Pass the same events iterator on every call, so that no input read ahead is lost.
+    pub fn read_line<I, W>(
+        &mut self,
+        prompt: &str,
+        events: I,
+        out: &mut W,
+    ) -> io::Result<Option<String>>
+    where
+        I: IntoIterator<Item = io::Result<Event>>,
+        W: Write,
+    {
+        let mut state = LineState {
+            prompt,
+            line: String::new(),
+            cursor: 0,
+            scroll: 0,
+            width: terminal_size().ok().map(|(width, _)| width as usize),
+            history_index: None,
+            draft: String::new(),
+            completion: None,
+        };
+        state.render(out)?;
+
+        for event in events {
+            let key = match event? {
+                Event::Key(key) => key,
+                Event::KeyEvent(key, kind) if kind != KeyEventKind::Release => key,
+                Event::Paste(text) => {
+                    let text: String = text
+                        .chars()
+                        .map(|c| if c.is_control() { ' ' } else { c })
+                        .collect();
+                    state.insert(&text);
+                    state.render(out)?;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if key != Key::Char('\t') {
+                state.completion = None;
+            }
+            match key {
+                Key::Char('\n') => {
+                    write!(out, "\r\n")?;
+                    self.history.add(&state.line);
+                    return Ok(Some(state.line));
+                }
+                Key::Ctrl('c') => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                Key::Ctrl('d') if state.line.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                Key::Char('\t') => self.complete(&mut state),
+                Key::Char(c) if !c.is_control() => state.insert(c.encode_utf8(&mut [0; 4])),
+                Key::Left | Key::Ctrl('b') => {
+                    state.cursor = prev_boundary(&state.line, state.cursor)
+                }
+                Key::Right | Key::Ctrl('f') => {
+                    state.cursor = next_boundary(&state.line, state.cursor)
+                }
+                Key::Home | Key::Ctrl('a') => state.cursor = 0,
+                Key::End | Key::Ctrl('e') => state.cursor = state.line.len(),
+                Key::Modified(KeyCode::Left, Modifiers::CTRL) | Key::Alt('b') => {
+                    state.cursor = word_start(&state.line, state.cursor, is_word_char)
+                }
+                Key::Modified(KeyCode::Right, Modifiers::CTRL) | Key::Alt('f') => {
+                    state.cursor = word_end(&state.line, state.cursor, is_word_char)
+                }
+                Key::Backspace | Key::Ctrl('h') => {
+                    let start = prev_boundary(&state.line, state.cursor);
+                    state.delete(start, state.cursor);
+                }
+                Key::Delete | Key::Ctrl('d') => {
+                    let end = next_boundary(&state.line, state.cursor);
+                    state.delete(state.cursor, end);
+                }
+                Key::Ctrl('k') => self.killed = state.delete(state.cursor, state.line.len()),
+                Key::Ctrl('u') => self.killed = state.delete(0, state.cursor),
+                Key::Ctrl('w') => {
+                    let start = word_start(&state.line, state.cursor, |c| !c.is_whitespace());
+                    self.killed = state.delete(start, state.cursor);
+                }
+                // Terminals without the kitty keyboard protocol send Alt and Backspace as ESC DEL.
+                Key::Modified(KeyCode::Backspace, Modifiers::ALT) | Key::Alt('\x7F') => {
+                    let start = word_start(&state.line, state.cursor, is_word_char);
+                    self.killed = state.delete(start, state.cursor);
+                }
+                Key::Alt('d') => {
+                    let end = word_end(&state.line, state.cursor, is_word_char);
+                    self.killed = state.delete(state.cursor, end);
+                }
+                Key::Ctrl('y') => state.insert(&self.killed),
+                Key::Up | Key::Ctrl('p') => self.previous_entry(&mut state),
+                Key::Down | Key::Ctrl('n') => self.next_entry(&mut state),
+                Key::Ctrl('l') => write!(out, "{}{}", clear::All, cursor::Goto(1, 1))?,
+                _ => continue,
+            }
+            state.render(out)?;
+        }
+
+        write!(out, "\r\n")?;
+        if state.line.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(state.line))
+        }
+    }
+
+    fn previous_entry(&self, state: &mut LineState) {
+        let index = match state.history_index {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                state.draft = state.line.clone();
+                self.history.len() - 1
+            }
+        };
+        if let Some(entry) = self.history.get(index) {
+            state.history_index = Some(index);
+            state.set_line(entry);
+        }
+    }
+
+    fn next_entry(&self, state: &mut LineState) {
+        let index = match state.history_index {
+            Some(index) => index + 1,
+            None => return,
+        };
+        match self.history.get(index) {
+            Some(entry) => {
+                state.history_index = Some(index);
+                state.set_line(entry);
+            }
+            None => {
+                state.history_index = None;
+                let draft = std::mem::take(&mut state.draft);
+                state.set_line(draft);
+            }
+        }
+    }
+
+    fn complete(&mut self, state: &mut LineState) {
+        if let Some(ref mut completion) = state.completion {
+            // Cycle through the candidates.
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            let (start, end) = (completion.start, state.cursor);
+            let candidate = completion.candidates[completion.index].clone();
+            state.replace(start, end, &candidate);
+            return;
+        }
+
+        let completer = match self.completer {
+            Some(ref mut completer) => completer,
+            None => return,
+        };
+        let (start, candidates) = completer(&state.line, state.cursor);
+        if candidates.is_empty() || start > state.cursor || !state.line.is_char_boundary(start) {
+            return;
+        }
+
+        let end = state.cursor;
+        if candidates.len() == 1 {
+            state.replace(start, end, &candidates[0]);
+            return;
+        }
+        let prefix = common_prefix(&candidates);
+        if prefix.len() > end - start {
+            state.replace(start, end, prefix);
+        } else {
+            state.replace(start, end, &candidates[0]);
+            state.completion = Some(Completion {
+                start,
+                candidates,
+                index: 0,
+            });
+        }
+    }
+}
+
+/// The state of the line being edited.
+struct LineState<'a> {
+    prompt: &'a str,
+    line: String,
+    /// The byte index of the cursor in `line`.
+    cursor: usize,
+    /// The byte index in `line` of the first character shown, when the line is wider than the
+    /// terminal.
+    scroll: usize,
+    /// The width of the terminal, if known.
+    width: Option<usize>,
+    /// The history entry shown, if any.
+    history_index: Option<usize>,
+    /// The line entered before moving through the history.
+    draft: String,
+    completion: Option<Completion>,
+}
+
+/// The candidates cycled through by pressing Tab repeatedly.
+struct Completion {
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl LineState<'_> {
+    fn insert(&mut self, text: &str) {
+        self.line.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Delete the text between two byte indices, returning it.
+    fn delete(&mut self, start: usize, end: usize) -> String {
+        self.cursor = start;
+        self.line.drain(start..end).collect()
+    }
+
+    fn replace(&mut self, start: usize, end: usize, text: &str) {
+        self.line.replace_range(start..end, text);
+        self.cursor = start + text.len();
+    }
+
+    fn set_line(&mut self, line: String) {
+        self.line = line;
+        self.cursor = self.line.len();
+        self.scroll = 0;
+    }
+
+    /// Redraw the prompt and line, scrolling the line horizontally to keep the cursor visible.
+    fn render<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        // Keep the last column free, so that the cursor does not wrap.
+        let available = self
+            .width
+            .map(|width| width.saturating_sub(grapheme::width(self.prompt) + 1));
+
+        let mut visible = &self.line[..];
+        if let Some(available) = available {
+            self.scroll = self.scroll.min(self.cursor);
+            while grapheme::width(&self.line[self.scroll..self.cursor]) > available {
+                self.scroll = next_boundary(&self.line, self.scroll);
+            }
+            visible = &self.line[self.scroll..];
+            while grapheme::width(visible) > available {
+                visible = &visible[..prev_boundary(visible, visible.len())];
+            }
+        }
+
+        write!(out, "\r{}{}{}", self.prompt, visible, clear::UntilNewline)?;
+        let after_cursor = grapheme::width(&visible[self.cursor - self.scroll..]);
+        if after_cursor > 0 {
+            write!(out, "{}", cursor::Left(after_cursor as u16))?;
+        }
+        out.flush()
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    // Combining marks belong to the letter before them.
+    c.is_alphanumeric() || grapheme::is_extend(c)
+}
+
+/// The start of the word before `pos`.
+fn word_start(s: &str, pos: usize, is_word: fn(char) -> bool) -> usize {
+    let mut start = pos;
+    let mut in_word = false;
+    for (i, c) in s[..pos].char_indices().rev() {
+        if is_word(c) {
+            in_word = true;
+        } else if in_word {
+            break;
+        }
+        start = i;
+    }
+    start
+}
+
+/// The end of the word after `pos`.
+fn word_end(s: &str, pos: usize, is_word: fn(char) -> bool) -> usize {
+    let mut end = pos;
+    let mut in_word = false;
+    for (i, c) in s[pos..].char_indices() {
+        if is_word(c) {
+            in_word = true;
+        } else if in_word {
+            return pos + i;
+        }
+        end = pos + i + c.len_utf8();
+    }
+    end
+}
+
+/// The longest common prefix of the candidates.
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = &candidates[0];
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|&((_, a), b)| a != b)
+            .map_or(len.min(candidate.len()), |((i, _), _)| i.min(len));
+    }
+    &first[..len]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use input::TermRead;
+
+    fn read<H: History>(editor: &mut LineEditor<H>, input: &[u8]) -> Option<String> {
+        let mut out = Vec::new();
+        editor.read_line("> ", input.events(), &mut out).unwrap()
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut editor = LineEditor::new();
+        assert_eq!(read(&mut editor, b"ab\x1B[Dc\r"), Some("acb".into()));
+        assert_eq!(
+            read(&mut editor, b"hello\x01X\x05Y\r"),
+            Some("XhelloY".into())
+        );
+        assert_eq!(
+            read(&mut editor, b"abc\x1B[D\x1B[D\x1B[3~\r"),
+            Some("ac".into())
+        );
+        assert_eq!(read(&mut editor, b"ab\x02\x02\x06\x04\r"), Some("a".into()));
+        assert_eq!(
+            read(&mut editor, "h\u{e9}\u{e9}\x7F\r".as_bytes()),
+            Some("h\u{e9}".into())
+        );
+        assert_eq!(
+            read(&mut editor, b"\x1B[200~a\nb\x1B[201~\r"),
+            Some("a b".into())
+        );
+    }
+
+    #[test]
+    fn test_graphemes() {
+        let mut editor = LineEditor::new();
+        assert_eq!(
+            read(&mut editor, "ae\u{301}b\x1B[D\x1B[D\x7F\r".as_bytes()),
+            Some("e\u{301}b".into())
+        );
+        assert_eq!(
+            read(&mut editor, "\u{65E5}\u{672C}\x7F\x01\x1B[3~x\r".as_bytes()),
+            Some("x".into())
+        );
+        assert_eq!(
+            read(
+                &mut editor,
+                "caf\u{E9}\u{301} \u{65E5}\x1Bb\x1Bb\x0B\r".as_bytes()
+            ),
+            Some("".into())
+        );
+    }
+
+    #[test]
+    fn test_words_and_killing() {
+        let mut editor = LineEditor::new();
+        assert_eq!(
+            read(&mut editor, b"one two three\x1Bb\x1Bb\x0B\x05 \x19\r"),
+            Some("one  two three".into())
+        );
+        assert_eq!(
+            read(&mut editor, b"one two\x1B[1;5D\x1B[1;5D\x1B[1;5C!\r"),
+            Some("one! two".into())
+        );
+        assert_eq!(
+            read(&mut editor, b"one two\x01\x1Bd\r"),
+            Some(" two".into())
+        );
+        assert_eq!(read(&mut editor, b"a-b c.d\x17\r"), Some("a-b ".into()));
+        assert_eq!(
+            read(&mut editor, b"a-b c.d\x1B\x7F\r"),
+            Some("a-b c.".into())
+        );
+        assert_eq!(read(&mut editor, b"abc\x15x\x19\r"), Some("xabc".into()));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut editor = LineEditor::new();
+        assert_eq!(read(&mut editor, b"first\r"), Some("first".into()));
+        assert_eq!(read(&mut editor, b"second\r"), Some("second".into()));
+        assert_eq!(read(&mut editor, b"second\r"), Some("second".into()));
+        assert_eq!(read(&mut editor, b"\r"), Some("".into()));
+        assert_eq!(editor.history().len(), 2);
+
+        assert_eq!(
+            read(&mut editor, b"\x1B[A\x1B[A\x1B[A\r"),
+            Some("first".into())
+        );
+        assert_eq!(read(&mut editor, b"draft\x10\x0E\r"), Some("draft".into()));
+        let entries: Vec<_> = (0..4).filter_map(|i| editor.history().get(i)).collect();
+        assert_eq!(entries, ["first", "second", "first", "draft"]);
+        assert_eq!(
+            read(&mut editor, b"\x1B[A\x1B[A\x1B[A\x1B[A\x1B[B!\r"),
+            Some("second!".into())
+        );
+
+        let mut history = MemoryHistory::with_max_len(1);
+        history.add("a");
+        history.add("b");
+        assert_eq!((history.len(), history.get(0)), (1, Some("b".into())));
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut editor = LineEditor::new().completer(|line, pos| {
+            let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+            let candidates = ["hello", "help", "world"]
+                .iter()
+                .filter(|word| word.starts_with(&line[start..pos]))
+                .map(|word| word.to_string())
+                .collect();
+            (start, candidates)
+        });
+        assert_eq!(read(&mut editor, b"say w\t\r"), Some("say world".into()));
+        assert_eq!(read(&mut editor, b"he\t\r"), Some("hel".into()));
+        assert_eq!(read(&mut editor, b"hel\t\r"), Some("hello".into()));
+        assert_eq!(read(&mut editor, b"hel\t\t\r"), Some("help".into()));
+        assert_eq!(read(&mut editor, b"hel\t\t\t\r"), Some("hello".into()));
+        assert_eq!(read(&mut editor, b"x\t\r"), Some("x".into()));
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut editor = LineEditor::new();
+        assert_eq!(read(&mut editor, b"abc\x03"), None);
+        assert_eq!(read(&mut editor, b"\x04"), None);
+        assert_eq!(read(&mut editor, b""), None);
+        assert_eq!(read(&mut editor, b"abc\x04"), Some("abc".into()));
+        assert!(editor.history().is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut state = LineState {
+            prompt: "> ",
+            line: "abcdefghijkl".into(),
+            cursor: 12,
+            scroll: 0,
+            width: Some(10),
+            history_index: None,
+            draft: String::new(),
+            completion: None,
+        };
+        let mut out = Vec::new();
+        state.render(&mut out).unwrap();
+        assert_eq!(out, b"\r> fghijkl\x1B[K");
+
+        state.cursor = 0;
+        out.clear();
+        state.render(&mut out).unwrap();
+        assert_eq!(out, b"\r> abcdefg\x1B[K\x1B[7D");
+
+        // Wide characters take two columns.
+        state.line = "\u{65E5}\u{672C}\u{8A9E}\u{65E5}".into();
+        state.cursor = 3;
+        out.clear();
+        state.render(&mut out).unwrap();
+        assert_eq!(out, "\r> \u{65E5}\u{672C}\u{8A9E}\x1B[K\x1B[4D".as_bytes());
+    }
+}
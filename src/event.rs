@@ -3,21 +3,114 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use std::error;
+use std::fmt;
 use std::io::{Error, ErrorKind};
+use std::ops;
 use std::str;
 
+use terminfo::Terminfo;
+
 /// An event reported by the terminal.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Event {
     /// A key press.
     Key(Key),
+    /// A key press, repeat or release.
+    ///
+    /// Key events are reported this way instead of as `Event::Key` by event iterators configured
+    /// with `ParseOptions::key_event_kinds`. Terminals only report repeats and releases when asked
+    /// to, see `input::KeyboardEnhancementFlags::REPORT_EVENT_TYPES`; other keys are presses.
+    KeyEvent(Key, KeyEventKind),
     /// A mouse button press, release or wheel use at specific coordinates.
     Mouse(MouseEvent),
+    /// A mouse event with coordinates in pixels rather than cells.
+    ///
+    /// Terminals report these once SGR-pixels mode is enabled, see
+    /// `input::MouseModes::sgr_pixels`. Since they look exactly like SGR mouse events, they are
+    /// only recognized by event iterators configured with `ParseOptions::pixel_mouse`. Use
+    /// `MouseEvent::pixels_to_cells` to get cell coordinates back.
+    MousePixels(MouseEvent),
+    /// Text pasted into the terminal while bracketed paste mode is enabled.
+    ///
+    /// See `input::BracketedPasteTerminal`.
+    Paste(String),
+    /// The terminal window gained focus.
+    ///
+    /// See `input::FocusTerminal`.
+    FocusGained,
+    /// The terminal window lost focus.
+    ///
+    /// See `input::FocusTerminal`.
+    FocusLost,
+    /// A reply to a query written to the terminal.
+    Response(Response),
     /// An event that cannot currently be evaluated.
     Unsupported(Vec<u8>),
 }
 
+/// A reply from the terminal to a query.
+///
+/// Queries are written to the terminal like any other escape sequence, and the terminal answers
+/// through its input, so replies arrive among the other events.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Response {
+    /// The cursor position, in reply to `CSI 6 n`.
+    ///
+    /// The coordinates are one-based, column first.
+    CursorPosition(u16, u16),
+    /// The primary device attributes, in reply to `CSI c`.
+    ///
+    /// The first parameter identifies the conformance level, the others the supported features.
+    PrimaryDeviceAttributes(Vec<u16>),
+    /// The secondary device attributes, in reply to `CSI > c`.
+    ///
+    /// The parameters are the terminal type, its firmware version and a hardware option.
+    SecondaryDeviceAttributes(Vec<u16>),
+    /// The state of an ANSI mode, in reply to `CSI mode $ p`.
+    Mode(u16, ModeState),
+    /// The state of a private (DEC) mode, in reply to `CSI ? mode $ p`.
+    PrivateMode(u16, ModeState),
+    /// A color, in reply to `OSC 4 ; index ; ?`, `OSC 10 ; ?`, `OSC 11 ; ?` or `OSC 12 ; ?`.
+    ///
+    /// The red, green and blue channels are scaled to 16 bits.
+    Color(ColorSlot, u16, u16, u16),
+    /// The name and version of the terminal, in reply to `CSI > q`.
+    TerminalVersion(String),
+}
+
+/// The state of a terminal mode, as reported by a mode request.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ModeState {
+    /// The terminal does not recognize the mode.
+    NotRecognized,
+    /// The mode is set.
+    Set,
+    /// The mode is reset.
+    Reset,
+    /// The mode is set and cannot be changed.
+    PermanentlySet,
+    /// The mode is reset and cannot be changed.
+    PermanentlyReset,
+}
+
+/// A color of the terminal that can be queried.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColorSlot {
+    /// An entry of the color palette.
+    Palette(u8),
+    /// The default foreground color.
+    Foreground,
+    /// The default background color.
+    Background,
+    /// The cursor color.
+    Cursor,
+}
+
 /// A mouse related event.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -25,15 +118,91 @@ pub enum MouseEvent {
     /// A mouse button was pressed.
     ///
     /// The coordinates are one-based.
-    Press(MouseButton, u16, u16),
+    Press(MouseButton, u16, u16, Modifiers),
     /// A mouse button was released.
     ///
-    /// The coordinates are one-based.
-    Release(u16, u16),
-    /// A mouse button is held over the given coordinates.
+    /// Only the SGR mouse encoding reports which button was released, the button is `None` for
+    /// the others. The coordinates are one-based.
+    Release(Option<MouseButton>, u16, u16, Modifiers),
+    /// A mouse button is held over the given coordinates, dragging the mouse.
     ///
     /// The coordinates are one-based.
-    Hold(u16, u16),
+    Hold(MouseButton, u16, u16, Modifiers),
+    /// The mouse moved over the given coordinates, with no button held.
+    ///
+    /// This is only reported with any-event tracking, see `input::MouseModes::any_event`. The
+    /// coordinates are one-based.
+    Moved(u16, u16, Modifiers),
+}
+
+impl MouseEvent {
+    /// The one-based coordinates of the event.
+    pub fn position(&self) -> (u16, u16) {
+        match *self {
+            MouseEvent::Press(_, x, y, _)
+            | MouseEvent::Release(_, x, y, _)
+            | MouseEvent::Hold(_, x, y, _)
+            | MouseEvent::Moved(x, y, _) => (x, y),
+        }
+    }
+
+    /// The modifier keys held during the event.
+    ///
+    /// Terminals only report Shift, Alt and Ctrl, and often reserve some combinations for
+    /// themselves.
+    pub fn modifiers(&self) -> Modifiers {
+        match *self {
+            MouseEvent::Press(_, _, _, modifiers)
+            | MouseEvent::Release(_, _, _, modifiers)
+            | MouseEvent::Hold(_, _, _, modifiers)
+            | MouseEvent::Moved(_, _, modifiers) => modifiers,
+        }
+    }
+
+    /// Convert the pixel coordinates of an `Event::MousePixels` event into cell coordinates.
+    ///
+    /// `size` is the size of the terminal in cells and `size_pixels` its size in pixels, as
+    /// returned by `terminal_size` and `terminal_size_pixels`. Returns `None` if the pixel size
+    /// is unknown, which some terminals report as zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use termion::event::Event;
+    ///
+    /// # let event = Event::FocusGained;
+    /// if let Event::MousePixels(me) = event {
+    ///     let size = termion::terminal_size()?;
+    ///     let size_pixels = termion::terminal_size_pixels()?;
+    ///     println!("{:?}", me.pixels_to_cells(size, size_pixels));
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn pixels_to_cells(self, size: (u16, u16), size_pixels: (u16, u16)) -> Option<MouseEvent> {
+        if size_pixels.0 == 0 || size_pixels.1 == 0 {
+            return None;
+        }
+
+        // Both kinds of coordinates are one-based.
+        let convert = |pixel: u16, cells: u16, pixels: u16| {
+            let cell = u32::from(pixel.saturating_sub(1)) * u32::from(cells) / u32::from(pixels);
+            cell.min(u32::from(cells.saturating_sub(1))) as u16 + 1
+        };
+        let (x, y) = self.position();
+        let x = convert(x, size.0, size_pixels.0);
+        let y = convert(y, size.1, size_pixels.1);
+
+        Some(match self {
+            MouseEvent::Press(button, _, _, modifiers) => {
+                MouseEvent::Press(button, x, y, modifiers)
+            }
+            MouseEvent::Release(button, _, _, modifiers) => {
+                MouseEvent::Release(button, x, y, modifiers)
+            }
+            MouseEvent::Hold(button, _, _, modifiers) => MouseEvent::Hold(button, x, y, modifiers),
+            MouseEvent::Moved(_, _, modifiers) => MouseEvent::Moved(x, y, modifiers),
+        })
+    }
 }
 
 /// A mouse button.
@@ -65,6 +234,10 @@ pub enum MouseButton {
 }
 
 /// A key.
+///
+/// Keys pressed together with modifiers that have no dedicated variant are reported as
+/// `Key::Modified`. Use `Key::new` to build a key from a `KeyCode` and `Modifiers`, and
+/// `Key::code` and `Key::modifiers` to take it apart again.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Key {
@@ -72,44 +245,16 @@ pub enum Key {
     Backspace,
     /// Left arrow.
     Left,
-    /// Shift Left arrow.
-    ShiftLeft,
-    /// Alt Left arrow.
-    AltLeft,
-    /// Ctrl Left arrow.
-    CtrlLeft,
     /// Right arrow.
     Right,
-    /// Shift Right arrow.
-    ShiftRight,
-    /// Alt Right arrow.
-    AltRight,
-    /// Ctrl Right arrow.
-    CtrlRight,
     /// Up arrow.
     Up,
-    /// Shift Up arrow.
-    ShiftUp,
-    /// Alt Up arrow.
-    AltUp,
-    /// Ctrl Up arrow.
-    CtrlUp,
     /// Down arrow.
     Down,
-    /// Shift Down arrow.
-    ShiftDown,
-    /// Alt Down arrow.
-    AltDown,
-    /// Ctrl Down arrow
-    CtrlDown,
     /// Home key.
     Home,
-    /// Ctrl Home key.
-    CtrlHome,
     /// End key.
     End,
-    /// Ctrl End key.
-    CtrlEnd,
     /// Page Up key.
     PageUp,
     /// Page Down key.
@@ -122,7 +267,8 @@ pub enum Key {
     Insert,
     /// Function keys.
     ///
-    /// Only function keys 1 through 12 are supported.
+    /// Function keys 1 through 20 are supported by most terminals. Terminals using the kitty
+    /// keyboard protocol can report up to F35.
     F(u8),
     /// Normal character.
     Char(char),
@@ -136,37 +282,705 @@ pub enum Key {
     Null,
     /// Esc key.
     Esc,
+    /// A key of the numeric keypad.
+    ///
+    /// The key is given by the character it types, with `'\n'` for Enter. Terminals only tell
+    /// keypad keys apart from the main keys in application keypad mode, see
+    /// `input::ApplicationKeypadTerminal`, or with the kitty keyboard protocol.
+    Keypad(char),
+    /// A key pressed together with a set of modifiers.
+    Modified(KeyCode, Modifiers),
+
+    #[doc(hidden)]
+    __IsNotComplete,
+}
+
+impl Key {
+    /// Build a key from a key code and a set of modifiers.
+    ///
+    /// The dedicated variants are used where they exist, so that for example Ctrl and `a` gives
+    /// `Key::Ctrl('a')` and a code without modifiers gives the corresponding plain key.
+    pub fn new(code: KeyCode, modifiers: Modifiers) -> Key {
+        match (code, modifiers) {
+            (code, m) if m.is_empty() => code.into(),
+            (KeyCode::Char(c), Modifiers::ALT) => Key::Alt(c),
+            (KeyCode::Char(c), Modifiers::CTRL) => Key::Ctrl(c),
+            (code, modifiers) => Key::Modified(code, modifiers),
+        }
+    }
+
+    /// The key code, without any modifiers.
+    pub fn code(&self) -> KeyCode {
+        match *self {
+            Key::Backspace => KeyCode::Backspace,
+            Key::Left => KeyCode::Left,
+            Key::Right => KeyCode::Right,
+            Key::Up => KeyCode::Up,
+            Key::Down => KeyCode::Down,
+            Key::Home => KeyCode::Home,
+            Key::End => KeyCode::End,
+            Key::PageUp => KeyCode::PageUp,
+            Key::PageDown => KeyCode::PageDown,
+            Key::BackTab => KeyCode::BackTab,
+            Key::Delete => KeyCode::Delete,
+            Key::Insert => KeyCode::Insert,
+            Key::F(n) => KeyCode::F(n),
+            Key::Char(c) | Key::Alt(c) | Key::Ctrl(c) => KeyCode::Char(c),
+            Key::Null => KeyCode::Null,
+            Key::Esc => KeyCode::Esc,
+            Key::Keypad(c) => KeyCode::Keypad(c),
+            Key::Modified(code, _) => code,
+            Key::__IsNotComplete => KeyCode::__IsNotComplete,
+        }
+    }
+
+    /// The modifiers held while the key was pressed.
+    pub fn modifiers(&self) -> Modifiers {
+        match *self {
+            Key::Alt(_) => Modifiers::ALT,
+            Key::Ctrl(_) => Modifiers::CTRL,
+            Key::Modified(_, modifiers) => modifiers,
+            _ => Modifiers::empty(),
+        }
+    }
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Key {
+        match code {
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Null => Key::Null,
+            KeyCode::Esc => Key::Esc,
+            KeyCode::Keypad(c) => Key::Keypad(c),
+            KeyCode::__IsNotComplete => Key::__IsNotComplete,
+        }
+    }
+}
+
+/// Whether a key was pressed, repeated or released.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KeyEventKind {
+    /// The key was pressed.
+    Press,
+    /// The key is held down, and repeats.
+    Repeat,
+    /// The key was released.
+    Release,
+}
+
+/// A key, without modifiers.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    /// Backspace.
+    Backspace,
+    /// Left arrow.
+    Left,
+    /// Right arrow.
+    Right,
+    /// Up arrow.
+    Up,
+    /// Down arrow.
+    Down,
+    /// Home key.
+    Home,
+    /// End key.
+    End,
+    /// Page Up key.
+    PageUp,
+    /// Page Down key.
+    PageDown,
+    /// Backward Tab key.
+    BackTab,
+    /// Delete key.
+    Delete,
+    /// Insert key.
+    Insert,
+    /// Function keys.
+    F(u8),
+    /// Normal character.
+    Char(char),
+    /// Null byte.
+    Null,
+    /// Esc key.
+    Esc,
+    /// A key of the numeric keypad.
+    Keypad(char),
 
     #[doc(hidden)]
     __IsNotComplete,
 }
 
+/// A set of modifier keys.
+///
+/// Modifiers can be combined with `|`. The bits follow the kitty keyboard protocol, which
+/// extends the modifier parameter used by xterm.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    /// The Shift key.
+    pub const SHIFT: Modifiers = Modifiers(1);
+    /// The Alt key.
+    pub const ALT: Modifiers = Modifiers(2);
+    /// The Ctrl key.
+    pub const CTRL: Modifiers = Modifiers(4);
+    /// The Super key.
+    pub const SUPER: Modifiers = Modifiers(8);
+    /// The Hyper key.
+    pub const HYPER: Modifiers = Modifiers(16);
+    /// The Meta key.
+    pub const META: Modifiers = Modifiers(32);
+
+    /// No modifiers.
+    pub fn empty() -> Modifiers {
+        Modifiers(0)
+    }
+
+    /// Build a set of modifiers from its bits, ignoring unknown bits.
+    pub fn from_bits_truncate(bits: u8) -> Modifiers {
+        Modifiers(bits & 0b11_1111)
+    }
+
+    /// The bits of this set of modifiers.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Whether no modifiers are set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all modifiers in `other` are set.
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Decode the modifier parameter of a control sequence, which is one plus the bits.
+    fn from_param(param: u16) -> Option<Modifiers> {
+        match param {
+            1..=256 => Some(Modifiers::from_bits_truncate((param - 1) as u8)),
+            _ => None,
+        }
+    }
+}
+
+impl ops::BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, rhs: Modifiers) -> Modifiers {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, rhs: Modifiers) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitAnd for Modifiers {
+    type Output = Modifiers;
+
+    fn bitand(self, rhs: Modifiers) -> Modifiers {
+        Modifiers(self.0 & rhs.0)
+    }
+}
+
+/// The modifiers of the key notation, in the order they are written.
+const MODIFIER_PREFIXES: [(char, Modifiers); 6] = [
+    ('C', Modifiers::CTRL),
+    ('M', Modifiers::ALT),
+    ('S', Modifiers::SHIFT),
+    ('s', Modifiers::SUPER),
+    ('H', Modifiers::HYPER),
+    ('T', Modifiers::META),
+];
+
+/// The names of the keys written in angle brackets.
+const KEY_NAMES: [(&str, KeyCode); 26] = [
+    ("Backspace", KeyCode::Backspace),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("BackTab", KeyCode::BackTab),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Null", KeyCode::Null),
+    ("Esc", KeyCode::Esc),
+    ("Enter", KeyCode::Char('\n')),
+    ("Tab", KeyCode::Char('\t')),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+    ("kEnter", KeyCode::Keypad('\n')),
+    ("kPlus", KeyCode::Keypad('+')),
+    ("kMinus", KeyCode::Keypad('-')),
+    ("kMultiply", KeyCode::Keypad('*')),
+    ("kDivide", KeyCode::Keypad('/')),
+    ("kPoint", KeyCode::Keypad('.')),
+    ("kEqual", KeyCode::Keypad('=')),
+    ("kComma", KeyCode::Keypad(',')),
+];
+
+/// Other accepted spellings of key names.
+const KEY_ALIASES: [(&str, KeyCode); 7] = [
+    ("gt", KeyCode::Char('>')),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Nul", KeyCode::Null),
+    ("CR", KeyCode::Char('\n')),
+    ("Return", KeyCode::Char('\n')),
+    ("Escape", KeyCode::Esc),
+];
+
+/// Keys are written in an Emacs and Vim style notation.
+///
+/// Modifiers are written as prefixes: `C-` for Ctrl, `M-` for Alt, `S-` for Shift, `s-` for
+/// Super, `H-` for Hyper and `T-` for Meta. Characters are written as themselves and other keys by
+/// their name in angle brackets, so Alt and Left is written `M-<Left>`. The names are those of
+/// `KeyCode`, plus `<Enter>`, `<Tab>`, `<Space>` and `<lt>` for the characters that are hard to
+/// read, and `<k0>`, `<kPlus>`, `<kEnter>` and so on for keypad keys.
+///
+/// A `Key::Modified` that the notation would give as another key, such as Shift and `a`, which is
+/// read as `A`, is written in full as `<Modified:S-a>`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{Key, KeyCode, Modifiers};
+///
+/// assert_eq!(Key::Ctrl('x').to_string(), "C-x");
+/// assert_eq!(Key::Modified(KeyCode::Left, Modifiers::ALT).to_string(), "M-<Left>");
+/// assert_eq!(Key::Modified(KeyCode::Char('a'), Modifiers::SHIFT).to_string(), "<Modified:S-a>");
+/// ```
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (code, modifiers) = match *self {
+            // Shift and Tab is reported as BackTab.
+            Key::BackTab => (KeyCode::Char('\t'), Modifiers::SHIFT),
+            Key::__IsNotComplete => return f.write_str("<IsNotComplete>"),
+            key => (key.code(), key.modifiers()),
+        };
+
+        if notation_key(code, modifiers) != *self {
+            f.write_str("<Modified:")?;
+            write_modifier_prefixes(f, modifiers)?;
+            return match code {
+                KeyCode::Char('>') => write!(f, "gt>"),
+                KeyCode::Char(c) if !KEY_NAMES.iter().any(|&(_, named)| named == code) => {
+                    write!(f, "{}>", c)
+                }
+                _ => write!(f, "{}>", KeyName(code)),
+            };
+        }
+
+        write_modifier_prefixes(f, modifiers)?;
+        match code {
+            KeyCode::Char(c) if !KEY_NAMES.iter().any(|&(_, named)| named == code) => {
+                write!(f, "{}", c)
+            }
+            _ => write!(f, "<{}>", KeyName(code)),
+        }
+    }
+}
+
+/// Write the prefixes of `modifiers` in the key notation.
+fn write_modifier_prefixes(f: &mut fmt::Formatter, modifiers: Modifiers) -> fmt::Result {
+    for &(prefix, modifier) in MODIFIER_PREFIXES.iter() {
+        if modifiers.contains(modifier) {
+            write!(f, "{}-", prefix)?;
+        }
+    }
+    Ok(())
+}
+
+/// The name of a key code in angle brackets, without the brackets.
+struct KeyName(KeyCode);
+
+impl fmt::Display for KeyName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(&(name, _)) = KEY_NAMES.iter().find(|&&(_, c)| c == self.0) {
+            return f.write_str(name);
+        }
+        match self.0 {
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Keypad('>') => f.write_str("kgt"),
+            KeyCode::Keypad(c) => write!(f, "k{}", c),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            _ => unreachable!("every other key code has a name"),
+        }
+    }
+}
+
+/// Keys are parsed from the notation they are displayed in.
+///
+/// The notation is parsed leniently: modifiers may also be written inside the angle brackets, as
+/// in `<S-F5>`, key names are not case sensitive, and a few other names are accepted, such as
+/// `<CR>` or `<Del>`. Shift and a character gives the uppercase character, as terminals report it.
+/// Every key is parsed back from its `Display` output.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{Key, KeyCode, Modifiers};
+///
+/// assert_eq!("C-x".parse(), Ok(Key::Ctrl('x')));
+/// assert_eq!("<S-F5>".parse(), Ok(Key::Modified(KeyCode::F(5), Modifiers::SHIFT)));
+/// ```
+impl str::FromStr for Key {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Key, ParseKeyError> {
+        match parse_key_notation(s) {
+            Some((key, "")) => Ok(key),
+            _ => Err(ParseKeyError(s.to_string())),
+        }
+    }
+}
+
+/// Parse a sequence of keys, such as `C-x C-s`.
+///
+/// Keys can be separated by whitespace or written next to each other, as in `<C-x><C-s>` or
+/// `gg`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{self, Key};
+///
+/// let keys = event::parse_key_sequence("C-x C-s")?;
+/// assert_eq!(keys, [Key::Ctrl('x'), Key::Ctrl('s')]);
+/// # Ok::<(), event::ParseKeyError>(())
+/// ```
+pub fn parse_key_sequence(s: &str) -> Result<Vec<Key>, ParseKeyError> {
+    let mut keys = Vec::new();
+    for word in s.split_whitespace() {
+        let mut rest = word;
+        while !rest.is_empty() {
+            let (key, after) =
+                parse_key_notation(rest).ok_or_else(|| ParseKeyError(word.to_string()))?;
+            keys.push(key);
+            rest = after;
+        }
+    }
+    Ok(keys)
+}
+
+/// Parse one key from the start of `s`, returning it with the rest of `s`.
+fn parse_key_notation(s: &str) -> Option<(Key, &str)> {
+    let (mut modifiers, rest) = parse_modifier_prefixes(s);
+
+    let (code, rest) = if let Some(bracketed) = rest.strip_prefix('<').filter(|b| b.contains('>')) {
+        let end = bracketed.find('>')?;
+        let (inner, rest) = (&bracketed[..end], &bracketed[end + 1..]);
+        if let Some(full) = inner.strip_prefix("Modified:") {
+            let (inner_modifiers, name) = parse_modifier_prefixes(full);
+            let code = parse_key_name(name)?;
+            return Some((Key::Modified(code, modifiers | inner_modifiers), rest));
+        }
+        let (inner_modifiers, name) = parse_modifier_prefixes(inner);
+        modifiers |= inner_modifiers;
+        (parse_key_name(name)?, rest)
+    } else {
+        let c = rest.chars().next()?;
+        (KeyCode::Char(c), &rest[c.len_utf8()..])
+    };
+
+    Some((notation_key(code, modifiers), rest))
+}
+
+/// The key written as `code` with `modifiers` in the key notation.
+fn notation_key(code: KeyCode, modifiers: Modifiers) -> Key {
+    match (code, modifiers) {
+        (KeyCode::Char('\t'), Modifiers::SHIFT) => Key::BackTab,
+        (KeyCode::Char(c), Modifiers::SHIFT) => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+        (code, modifiers) => Key::new(code, modifiers),
+    }
+}
+
+/// Strip the modifier prefixes from the start of `s`, leaving at least the key.
+fn parse_modifier_prefixes(mut s: &str) -> (Modifiers, &str) {
+    let mut modifiers = Modifiers::empty();
+    loop {
+        let mut chars = s.chars();
+        let prefix = match (chars.next(), chars.next(), chars.as_str()) {
+            (Some(prefix), Some('-'), rest) if !rest.is_empty() => prefix,
+            _ => return (modifiers, s),
+        };
+        match MODIFIER_PREFIXES.iter().find(|&&(p, _)| p == prefix) {
+            Some(&(_, modifier)) => modifiers |= modifier,
+            None => return (modifiers, s),
+        }
+        s = &s[2..];
+    }
+}
+
+/// Parse the name of a key written in angle brackets.
+fn parse_key_name(name: &str) -> Option<KeyCode> {
+    let named = KEY_NAMES.iter().chain(KEY_ALIASES.iter());
+    for &(known, code) in named {
+        if known.eq_ignore_ascii_case(name) {
+            return Some(code);
+        }
+    }
+
+    let mut chars = name.chars();
+    match (chars.next(), chars.as_str()) {
+        (Some(c), "") => Some(KeyCode::Char(c)),
+        (Some('F'), n) | (Some('f'), n) if n.bytes().all(|b| b.is_ascii_digit()) => {
+            n.parse().ok().map(KeyCode::F)
+        }
+        (Some('k'), "gt") | (Some('K'), "gt") => Some(KeyCode::Keypad('>')),
+        (Some('k'), c) | (Some('K'), c) if c.chars().count() == 1 => {
+            Some(KeyCode::Keypad(c.chars().next()?))
+        }
+        _ => None,
+    }
+}
+
+/// An error from parsing a key in the notation of `Key`'s `Display` implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError(String);
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid key notation: {:?}", self.0)
+    }
+}
+
+impl error::Error for ParseKeyError {}
+
+/// Settings changing how input is parsed.
+///
+/// They are given to the event readers of the `input` and `stream` modules through their
+/// `options` method.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::ParseOptions;
+/// use termion::input::TermRead;
+///
+/// let options = ParseOptions::default().key_event_kinds(true);
+/// let events = b"\x1B[97;1:3u".events().options(options);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pixel_mouse: bool,
+    key_event_kinds: bool,
+    terminfo: Option<Terminfo>,
+}
+
+impl ParseOptions {
+    /// Report SGR mouse events as `Event::MousePixels`.
+    ///
+    /// Enable this when SGR-pixels mode is enabled, see `MouseModes::sgr_pixels`.
+    pub fn pixel_mouse(mut self, enabled: bool) -> ParseOptions {
+        self.pixel_mouse = enabled;
+        self
+    }
+
+    /// Report keys as `Event::KeyEvent`, telling presses, repeats and releases apart.
+    ///
+    /// Terminals only report repeats and releases with
+    /// `KeyboardEnhancementFlags::REPORT_EVENT_TYPES` enabled.
+    pub fn key_event_kinds(mut self, enabled: bool) -> ParseOptions {
+        self.key_event_kinds = enabled;
+        self
+    }
+
+    /// Recognize the key sequences of a terminfo entry, before the built-in ones.
+    ///
+    /// See the `terminfo` module.
+    pub fn terminfo(mut self, terminfo: Option<Terminfo>) -> ParseOptions {
+        self.terminfo = terminfo;
+        self
+    }
+
+    /// The event reporting `key`, of the given kind.
+    pub(crate) fn key_event(&self, key: Key, kind: KeyEventKind) -> Event {
+        if self.key_event_kinds {
+            Event::KeyEvent(key, kind)
+        } else {
+            Event::Key(key)
+        }
+    }
+}
+
 /// Parse an Event from `item` and possibly subsequent bytes through `iter`.
+///
+/// Telling some keys from terminal replies takes reading ahead: Alt+] is only known not to start
+/// a reply once the next byte was read. Such bytes are not part of the event, and are lost, see
+/// `input::InputParser` for a parser keeping them.
 pub fn parse_event<I>(item: u8, iter: &mut I) -> Result<Event, Error>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    parse_event_with(
+        item,
+        iter,
+        &ParseOptions::default(),
+        usize::MAX,
+        &mut Vec::new(),
+    )
+}
+
+/// Parse an Event like `parse_event`, according to `options`.
+///
+/// The first `buffered` bytes of `iter` are available without waiting for input. Only those are
+/// read ahead to tell keys from replies, so that a key is never held back until the next one.
+///
+/// Bytes read ahead that are not part of the event are added to `unread`, in order. They are the
+/// last bytes read, and start the next event.
+pub(crate) fn parse_event_with<I>(
+    item: u8,
+    iter: &mut I,
+    options: &ParseOptions,
+    buffered: usize,
+    unread: &mut Vec<u8>,
+) -> Result<Event, Error>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    let mut consumed = Vec::new();
+    if let Some(ref terminfo) = options.terminfo {
+        match parse_terminfo_key(item, iter, terminfo)? {
+            Ok(key) => return Ok(options.key_event(key, KeyEventKind::Press)),
+            Err(bytes) => consumed = bytes,
+        }
+    }
+    // Parse the bytes read while looking for a terminfo sequence again. They are all available
+    // now, followed by what is left of the buffered bytes.
+    let buffered = buffered.max(consumed.len());
+    let mut consumed = consumed.into_iter();
+    let event = {
+        let mut iter = consumed.by_ref().map(Ok).chain(iter);
+        parse_any_event(item, &mut iter, options, buffered, unread)?
+    };
+    // The bytes past the event start the next one.
+    unread.extend(consumed);
+
+    Ok(match event {
+        // Only the kitty keyboard protocol reports other kinds of key events.
+        Event::Key(key) => options.key_event(key, KeyEventKind::Press),
+        event => event,
+    })
+}
+
+/// Parse a key sequence from a terminfo entry.
+///
+/// Bytes are only read while they may still complete a sequence. If none is completed, the bytes
+/// read after `item` are returned instead.
+fn parse_terminfo_key<I>(
+    item: u8,
+    iter: &mut I,
+    terminfo: &Terminfo,
+) -> Result<Result<Key, Vec<u8>>, Error>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    let mut seq = vec![item];
+    loop {
+        if let Some(key) = terminfo.key(&seq) {
+            return Ok(Ok(key));
+        }
+        if !terminfo.is_prefix(&seq) {
+            break;
+        }
+        match iter.next() {
+            Some(Ok(byte)) => seq.push(byte),
+            Some(Err(e)) => return Err(e),
+            None => break,
+        }
+    }
+    seq.remove(0);
+    Ok(Err(seq))
+}
+
+/// Parse an Event, reporting keys as `Event::Key` unless their kind is known.
+fn parse_any_event<I>(
+    item: u8,
+    iter: &mut I,
+    options: &ParseOptions,
+    buffered: usize,
+    unread: &mut Vec<u8>,
+) -> Result<Event, Error>
 where
     I: Iterator<Item = Result<u8, Error>>,
 {
     let error = Error::new(ErrorKind::Other, "Could not parse an event");
+    // The bytes available after the one following ESC.
+    let ahead = buffered.saturating_sub(1);
     match item {
         b'\x1B' => {
             // This is an escape character, leading a control sequence.
             Ok(match iter.next() {
                 Some(Ok(b'O')) => {
-                    match iter.next() {
-                        // F1-F4
-                        Some(Ok(val @ b'P'..=b'S')) => Event::Key(Key::F(1 + val - b'P')),
-                        _ => return Err(error),
-                    }
+                    // This is an SS3 sequence.
+                    parse_ss3(iter).ok_or(error)?
                 }
                 Some(Ok(b'[')) => {
                     // This is a CSI sequence.
-                    parse_csi(iter).ok_or(error)?
+                    parse_csi(iter, options).ok_or(error)?
+                }
+                Some(Ok(b']')) => {
+                    // This is an OSC sequence if a reply follows, and Alt+] otherwise.
+                    match read_reply_start(iter, ahead, starts_osc_reply)? {
+                        Ok(mut buf) => read_control_string(iter)
+                            .and_then(|rest| {
+                                buf.extend(rest);
+                                parse_osc(&buf)
+                            })
+                            .ok_or(error)?,
+                        Err(ahead) => {
+                            unread.extend(ahead);
+                            Event::Key(Key::Alt(']'))
+                        }
+                    }
+                }
+                Some(Ok(b'P')) => {
+                    // This is a DCS sequence if a reply follows, and Alt+P otherwise.
+                    match read_reply_start(iter, ahead, starts_dcs_reply)? {
+                        Ok(mut buf) => read_control_string(iter)
+                            .and_then(|rest| {
+                                buf.extend(rest);
+                                parse_dcs(&buf)
+                            })
+                            .ok_or(error)?,
+                        Err(ahead) => {
+                            unread.extend(ahead);
+                            Event::Key(Key::Alt('P'))
+                        }
+                    }
                 }
                 Some(Ok(c)) => {
                     let ch = parse_utf8_char(c, iter)?;
                     Event::Key(Key::Alt(ch))
                 }
-                Some(Err(_)) | None => return Err(error),
+                // An ESC ending the input is the Esc key.
+                None => Event::Key(Key::Esc),
+                Some(Err(_)) => return Err(error),
             })
         }
         b'\n' | b'\r' => Ok(Event::Key(Key::Char('\n'))),
@@ -182,10 +996,54 @@ where
     }
 }
 
+/// Parses an SS3 sequence, just after reading ^[O
+///
+/// These are sent for F1-F4, and for the cursor keys and keypad keys in application cursor key
+/// and application keypad mode. Some terminals add a modifier parameter, as in ^[O5P.
+fn parse_ss3<I>(iter: &mut I) -> Option<Event>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    let mut buf = Vec::new();
+    let mut c = iter.next()?.ok()?;
+    while matches!(c, b'0'..=b'9' | b';') {
+        buf.push(c);
+        c = iter.next()?.ok()?;
+    }
+
+    let modifiers = match parse_params(&buf)?[..] {
+        [modifiers] | [1, modifiers] => Modifiers::from_param(modifiers)?,
+        _ => return None,
+    };
+    let code = match c {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P'..=b'S' => KeyCode::F(1 + c - b'P'),
+        // Ctrl modified arrow keys as sent by rxvt.
+        b'a'..=b'd' => return Some(Event::Key(Key::new(arrow_key_code(c)?, Modifiers::CTRL))),
+        b'M' => KeyCode::Keypad('\n'),
+        b'X' => KeyCode::Keypad('='),
+        b'j' => KeyCode::Keypad('*'),
+        b'k' => KeyCode::Keypad('+'),
+        b'l' => KeyCode::Keypad(','),
+        b'm' => KeyCode::Keypad('-'),
+        b'n' => KeyCode::Keypad('.'),
+        b'o' => KeyCode::Keypad('/'),
+        b'p'..=b'y' => KeyCode::Keypad((b'0' + c - b'p') as char),
+        _ => return None,
+    };
+
+    Some(Event::Key(Key::new(code, modifiers)))
+}
+
 /// Parses a CSI sequence, just after reading ^[
 ///
 /// Returns None if an unrecognized sequence is found.
-fn parse_csi<I>(iter: &mut I) -> Option<Event>
+fn parse_csi<I>(iter: &mut I, options: &ParseOptions) -> Option<Event>
 where
     I: Iterator<Item = Result<u8, Error>>,
 {
@@ -201,203 +1059,206 @@ where
         Some(Ok(b'H')) => Event::Key(Key::Home),
         Some(Ok(b'F')) => Event::Key(Key::End),
         Some(Ok(b'Z')) => Event::Key(Key::BackTab),
+        // Shifted arrow keys as sent by rxvt.
+        Some(Ok(c @ b'a'..=b'd')) => Event::Key(Key::new(arrow_key_code(c)?, Modifiers::SHIFT)),
+        Some(Ok(b'I')) => Event::FocusGained,
+        Some(Ok(b'O')) => Event::FocusLost,
+        // F1, F2 and F4 as sent by terminals using the kitty keyboard protocol.
+        Some(Ok(b'P')) => Event::Key(Key::F(1)),
+        Some(Ok(b'Q')) => Event::Key(Key::F(2)),
+        Some(Ok(b'S')) => Event::Key(Key::F(4)),
         Some(Ok(b'M')) => {
             // X10 emulation mouse encoding: ESC [ CB Cx Cy (6 characters only).
-            let mut next = || iter.next().unwrap().unwrap();
+            //
+            // With the UTF-8 extension, values above 95 are sent as two byte
+            // UTF-8 characters instead. A byte that could start such a
+            // character but is not followed by a continuation byte is taken as
+            // a plain value, and the byte after it as the next value.
+            let mut values = [0u16; 3];
+            let mut carry = None;
+            for value in values.iter_mut() {
+                let c = match carry.take() {
+                    Some(c) => c,
+                    None => iter.next()?.ok()?,
+                };
+                *value = match c {
+                    0xC2..=0xDF => {
+                        let c2 = iter.next()?.ok()?;
+                        if c2 & 0xC0 == 0x80 {
+                            (c as u16 & 0x1F) << 6 | (c2 as u16 & 0x3F)
+                        } else {
+                            carry = Some(c2);
+                            c as u16
+                        }
+                    }
+                    c => c as u16,
+                };
+            }
+            if carry.is_some() {
+                return None;
+            }
 
-            let cb = next() as i8 - 32;
+            let cb = values[0].wrapping_sub(32);
             // (1, 1) are the coords for upper left.
-            let cx = next().saturating_sub(32) as u16;
-            let cy = next().saturating_sub(32) as u16;
-            Event::Mouse(match cb & 0b11 {
-                0 => {
-                    if cb & 0x40 != 0 {
-                        MouseEvent::Press(MouseButton::WheelUp, cx, cy)
-                    } else {
-                        MouseEvent::Press(MouseButton::Left, cx, cy)
-                    }
-                }
-                1 => {
-                    if cb & 0x40 != 0 {
-                        MouseEvent::Press(MouseButton::WheelDown, cx, cy)
-                    } else {
-                        MouseEvent::Press(MouseButton::Middle, cx, cy)
-                    }
-                }
-                2 => {
-                    if cb & 0x40 != 0 {
-                        MouseEvent::Press(MouseButton::WheelLeft, cx, cy)
-                    } else {
-                        MouseEvent::Press(MouseButton::Right, cx, cy)
-                    }
-                }
-                3 => {
-                    if cb & 0x40 != 0 {
-                        MouseEvent::Press(MouseButton::WheelRight, cx, cy)
-                    } else {
-                        MouseEvent::Release(cx, cy)
-                    }
-                }
-                _ => return None,
-            })
+            let cx = values[1].saturating_sub(32);
+            let cy = values[2].saturating_sub(32);
+
+            Event::Mouse(parse_mouse_button(cb, cx, cy, false)?)
         }
         Some(Ok(b'<')) => {
             // xterm mouse encoding:
             // ESC [ < Cb ; Cx ; Cy (;) (M or m)
             let mut buf = Vec::new();
-            let mut c = iter.next().unwrap().unwrap();
-            while match c {
-                b'm' | b'M' => false,
-                _ => true,
-            } {
+            let mut c = iter.next()?.ok()?;
+            while !matches!(c, b'm' | b'M') {
+                if !matches!(c, b'0'..=b'9' | b';') {
+                    return None;
+                }
                 buf.push(c);
-                c = iter.next().unwrap().unwrap();
+                c = iter.next()?.ok()?;
             }
-            let str_buf = String::from_utf8(buf).unwrap();
+            let str_buf = str::from_utf8(&buf).ok()?;
             let nums = &mut str_buf.split(';');
 
-            let cb = nums.next().unwrap().parse::<u16>().unwrap();
-            let cx = nums.next().unwrap().parse::<u16>().unwrap();
-            let cy = nums.next().unwrap().parse::<u16>().unwrap();
-
-            let event = match cb {
-                0..=2 | 64..=67 => {
-                    let button = match cb {
-                        0 => MouseButton::Left,
-                        1 => MouseButton::Middle,
-                        2 => MouseButton::Right,
-                        64 => MouseButton::WheelUp,
-                        65 => MouseButton::WheelDown,
-                        66 => MouseButton::WheelLeft,
-                        67 => MouseButton::WheelRight,
-                        _ => unreachable!(),
-                    };
-                    match c {
-                        b'M' => MouseEvent::Press(button, cx, cy),
-                        b'm' => MouseEvent::Release(cx, cy),
-                        _ => return None,
-                    }
+            let cb = nums.next()?.parse::<u16>().ok()?;
+            let cx = nums.next()?.parse::<u16>().ok()?;
+            let cy = nums.next()?.parse::<u16>().ok()?;
+
+            let event = parse_mouse_button(cb, cx, cy, c == b'm')?;
+            if options.pixel_mouse {
+                Event::MousePixels(event)
+            } else {
+                Event::Mouse(event)
+            }
+        }
+        Some(Ok(b'?')) => {
+            // Private reply: ESC [ ? params final
+            let (buf, c) = read_csi_params(iter, Vec::new())?;
+
+            Event::Response(match c {
+                b'c' => Response::PrimaryDeviceAttributes(parse_params(&buf)?),
+                b'y' => {
+                    let (mode, state) = parse_mode_report(&buf)?;
+                    Response::PrivateMode(mode, state)
                 }
-                32 => MouseEvent::Hold(cx, cy),
-                3 => MouseEvent::Release(cx, cy),
                 _ => return None,
-            };
-
-            Event::Mouse(event)
+            })
+        }
+        Some(Ok(b'>')) => {
+            // Secondary device attributes: ESC [ > params c
+            match read_csi_params(iter, Vec::new())? {
+                (buf, b'c') => {
+                    Event::Response(Response::SecondaryDeviceAttributes(parse_params(&buf)?))
+                }
+                _ => return None,
+            }
         }
         Some(Ok(c @ b'0'..=b'9')) => {
             // Numbered escape code.
-            let mut buf = Vec::new();
-            buf.push(c);
-            let mut c = iter.next().unwrap().unwrap();
-            // The final byte of a CSI sequence can be in the range 64-126, so
-            // let's keep reading anything else.
-            while c < 64 || c > 126 {
+            let mut buf = vec![c];
+            let mut c = iter.next()?.ok()?;
+            while c.is_ascii_digit() {
                 buf.push(c);
-                c = iter.next().unwrap().unwrap();
+                c = iter.next()?.ok()?;
             }
+            // rxvt ends shifted special keys with the intermediate byte $, which is otherwise
+            // only used after several parameters.
+            let (buf, c) = match c {
+                b'$' | 64..=126 => (buf, c),
+                32..=63 => {
+                    buf.push(c);
+                    read_csi_params(iter, buf)?
+                }
+                _ => return None,
+            };
 
             match c {
+                // Cursor position report:
+                // ESC [ Cy ; Cx R
+                b'R' => {
+                    let nums = parse_params(&buf)?;
+
+                    if nums.len() != 2 {
+                        return None;
+                    }
+
+                    Event::Response(Response::CursorPosition(nums[1], nums[0]))
+                }
+                // Mode report:
+                // ESC [ mode ; state $ y
+                b'y' => {
+                    let (mode, state) = parse_mode_report(&buf)?;
+                    Event::Response(Response::Mode(mode, state))
+                }
                 // rxvt mouse encoding:
                 // ESC [ Cb ; Cx ; Cy ; M
                 b'M' => {
-                    let str_buf = String::from_utf8(buf).unwrap();
+                    let nums = parse_params(&buf)?;
 
-                    let nums: Vec<u16> = str_buf.split(';').map(|n| n.parse().unwrap()).collect();
+                    if nums.len() != 3 {
+                        return None;
+                    }
 
-                    let cb = nums[0];
+                    let cb = nums[0].checked_sub(32)?;
                     let cx = nums[1];
                     let cy = nums[2];
 
-                    let event = match cb {
-                        32 => MouseEvent::Press(MouseButton::Left, cx, cy),
-                        33 => MouseEvent::Press(MouseButton::Middle, cx, cy),
-                        34 => MouseEvent::Press(MouseButton::Right, cx, cy),
-                        35 => MouseEvent::Release(cx, cy),
-                        64 => MouseEvent::Hold(cx, cy),
-                        96 | 97 => MouseEvent::Press(MouseButton::WheelUp, cx, cy),
-                        _ => return None,
-                    };
-
-                    Event::Mouse(event)
+                    Event::Mouse(parse_mouse_button(cb, cx, cy, false)?)
                 }
-                // Special key code.
+                // kitty keyboard protocol:
+                // ESC [ code (: shifted : base) ; modifiers (: event) ; text u
+                b'u' => parse_kitty_key(&buf, options)?,
+                // Special key code, optionally followed by a modifier parameter.
                 b'~' => {
-                    let str_buf = String::from_utf8(buf).unwrap();
+                    let (nums, kind) = parse_key_params(&buf, options)?;
 
-                    // This CSI sequence can be a list of semicolon-separated
-                    // numbers.
-                    let nums: Vec<u8> = str_buf.split(';').map(|n| n.parse().unwrap()).collect();
-
-                    if nums.is_empty() {
-                        return None;
-                    }
-
-                    // TODO: handle multiple values for key modififiers (ex: values
-                    // [3, 2] means Shift+Delete)
-                    if nums.len() > 1 {
-                        return None;
+                    if nums == [200] {
+                        return parse_paste(iter);
                     }
 
-                    match nums[0] {
-                        1 | 7 => Event::Key(Key::Home),
-                        2 => Event::Key(Key::Insert),
-                        3 => Event::Key(Key::Delete),
-                        4 | 8 => Event::Key(Key::End),
-                        5 => Event::Key(Key::PageUp),
-                        6 => Event::Key(Key::PageDown),
-                        v @ 11..=15 => Event::Key(Key::F(v - 10)),
-                        v @ 17..=21 => Event::Key(Key::F(v - 11)),
-                        v @ 23..=24 => Event::Key(Key::F(v - 12)),
+                    let code = special_key_code(*nums.first()?)?;
+                    let modifiers = match nums.len() {
+                        1 => Modifiers::empty(),
+                        2 => Modifiers::from_param(nums[1])?,
                         _ => return None,
-                    }
+                    };
+
+                    options.key_event(Key::new(code, modifiers), kind)
                 }
-                b'A' | b'B' | b'C' | b'D' | b'F' | b'H' => {
-                    let str_buf = String::from_utf8(buf).unwrap();
+                // Special key code with rxvt modifiers: ESC [ code $ for Shift, ESC [ code ^
+                // for Ctrl and ESC [ code @ for both.
+                b'$' | b'^' | b'@' => {
+                    let code = special_key_code(parse_params(&buf)?[0])?;
+                    let modifiers = match c {
+                        b'$' => Modifiers::SHIFT,
+                        b'^' => Modifiers::CTRL,
+                        _ => Modifiers::CTRL | Modifiers::SHIFT,
+                    };
 
-                    // This CSI sequence can be a list of semicolon-separated
-                    // numbers.
-                    let nums: Vec<u8> = str_buf.split(';').map(|n| n.parse().unwrap()).collect();
+                    Event::Key(Key::new(code, modifiers))
+                }
+                // Cursor key or F1-F4 with a modifier parameter: ESC [ 1 ; modifiers X
+                b'A' | b'B' | b'C' | b'D' | b'F' | b'H' | b'P' | b'Q' | b'S' => {
+                    let (nums, kind) = parse_key_params(&buf, options)?;
 
                     if !(nums.len() == 2 && nums[0] == 1) {
                         return None;
                     }
 
-                    match nums[1] {
-                        2 => {
-                            // Shift Modifier
-                            match c {
-                                b'D' => Event::Key(Key::ShiftLeft),
-                                b'C' => Event::Key(Key::ShiftRight),
-                                b'A' => Event::Key(Key::ShiftUp),
-                                b'B' => Event::Key(Key::ShiftDown),
-                                _ => return None,
-                            }
-                        }
-                        3 => {
-                            // Alt Modifier
-                            match c {
-                                b'D' => Event::Key(Key::AltLeft),
-                                b'C' => Event::Key(Key::AltRight),
-                                b'A' => Event::Key(Key::AltUp),
-                                b'B' => Event::Key(Key::AltDown),
-                                _ => return None,
-                            }
-                        }
-                        5 => {
-                            // Ctrl Modifier
-                            match c {
-                                b'D' => Event::Key(Key::CtrlLeft),
-                                b'C' => Event::Key(Key::CtrlRight),
-                                b'A' => Event::Key(Key::CtrlUp),
-                                b'B' => Event::Key(Key::CtrlDown),
-                                b'H' => Event::Key(Key::CtrlHome),
-                                b'F' => Event::Key(Key::CtrlEnd),
-                                _ => return None,
-                            }
-                        }
+                    let code = match c {
+                        b'D' => KeyCode::Left,
+                        b'C' => KeyCode::Right,
+                        b'A' => KeyCode::Up,
+                        b'B' => KeyCode::Down,
+                        b'H' => KeyCode::Home,
+                        b'F' => KeyCode::End,
+                        b'P' => KeyCode::F(1),
+                        b'Q' => KeyCode::F(2),
+                        b'S' => KeyCode::F(4),
                         _ => return None,
-                    }
+                    };
+
+                    options.key_event(Key::new(code, Modifiers::from_param(nums[1])?), kind)
                 }
                 _ => return None,
             }
@@ -406,6 +1267,379 @@ where
     })
 }
 
+/// The key sent as ESC [ code ~, or with rxvt modifiers.
+fn special_key_code(code: u16) -> Option<KeyCode> {
+    Some(match code {
+        1 | 7 => KeyCode::Home,
+        2 => KeyCode::Insert,
+        3 => KeyCode::Delete,
+        4 | 8 => KeyCode::End,
+        5 => KeyCode::PageUp,
+        6 => KeyCode::PageDown,
+        // The function key codes skip 16, 22, 27 and 30.
+        11..=15 => KeyCode::F(code as u8 - 10),
+        17..=21 => KeyCode::F(code as u8 - 11),
+        23..=26 => KeyCode::F(code as u8 - 12),
+        28..=29 => KeyCode::F(code as u8 - 13),
+        31..=34 => KeyCode::F(code as u8 - 14),
+        _ => return None,
+    })
+}
+
+/// The arrow key of the lowercase finals used by rxvt for modified arrow keys.
+fn arrow_key_code(c: u8) -> Option<KeyCode> {
+    Some(match c {
+        b'a' => KeyCode::Up,
+        b'b' => KeyCode::Down,
+        b'c' => KeyCode::Right,
+        b'd' => KeyCode::Left,
+        _ => return None,
+    })
+}
+
+/// Parses the parameters of a kitty keyboard protocol `CSI u` sequence.
+///
+/// Returns None for key events that cannot be represented by `Key`, and for release events unless
+/// key event kinds are reported.
+fn parse_kitty_key(buf: &[u8], options: &ParseOptions) -> Option<Event> {
+    let str_buf = str::from_utf8(buf).ok()?;
+    let mut fields = str_buf.split(';');
+
+    let mut codes = fields.next()?.split(':');
+    let code: u32 = codes.next()?.parse().ok()?;
+    let shifted: Option<u32> = match codes.next() {
+        Some("") | None => None,
+        Some(shifted) => Some(shifted.parse().ok()?),
+    };
+
+    let (modifiers, event_type) = match fields.next() {
+        Some(field) => {
+            let mut parts = field.split(':');
+            let modifiers = match parts.next() {
+                Some("") | None => 1,
+                Some(modifiers) => modifiers.parse::<u16>().ok()?,
+            };
+            let event_type = match parts.next() {
+                Some(event_type) => event_type.parse::<u8>().ok()?,
+                None => 1,
+            };
+            (modifiers, event_type)
+        }
+        None => (1, 1),
+    };
+
+    let kind = kitty_event_kind(event_type, options)?;
+
+    // Caps lock and num lock are not reported as modifiers.
+    let modifiers = Modifiers::from_param(modifiers)?;
+
+    let code = match code {
+        27 => KeyCode::Esc,
+        13 => KeyCode::Char('\n'),
+        9 => KeyCode::Char('\t'),
+        127 => KeyCode::Backspace,
+        57376..=57398 => KeyCode::F((code - 57376 + 13) as u8),
+        57399..=57408 => KeyCode::Keypad(char::from_u32(u32::from(b'0') + code - 57399)?),
+        57409 => KeyCode::Keypad('.'),
+        57410 => KeyCode::Keypad('/'),
+        57411 => KeyCode::Keypad('*'),
+        57412 => KeyCode::Keypad('-'),
+        57413 => KeyCode::Keypad('+'),
+        57414 => KeyCode::Keypad('\n'),
+        57415 => KeyCode::Keypad('='),
+        57416 => KeyCode::Keypad(','),
+        // The navigation keys of the keypad.
+        57417 => KeyCode::Left,
+        57418 => KeyCode::Right,
+        57419 => KeyCode::Up,
+        57420 => KeyCode::Down,
+        57421 => KeyCode::PageUp,
+        57422 => KeyCode::PageDown,
+        57423 => KeyCode::Home,
+        57424 => KeyCode::End,
+        57425 => KeyCode::Insert,
+        57426 => KeyCode::Delete,
+        // The rest of the private use area is used for functional keys without a legacy encoding.
+        57344..=63743 => return None,
+        _ => KeyCode::Char(char::from_u32(code)?),
+    };
+
+    let key = match (code, modifiers) {
+        (KeyCode::Char('\t'), Modifiers::SHIFT) => Key::BackTab,
+        (KeyCode::Char(c), Modifiers::SHIFT) => Key::Char(match shifted.and_then(char::from_u32) {
+            Some(shifted) => shifted,
+            None => c.to_uppercase().next().unwrap_or(c),
+        }),
+        (code, modifiers) => Key::new(code, modifiers),
+    };
+
+    Some(options.key_event(key, kind))
+}
+
+/// Parses the parameters of a key sequence, as by `parse_params`, along with the kind of key
+/// event.
+///
+/// With the kitty keyboard protocol, the event type follows the modifier parameter: ESC [ 1 ;
+/// modifiers : event A.
+fn parse_key_params(buf: &[u8], options: &ParseOptions) -> Option<(Vec<u16>, KeyEventKind)> {
+    let str_buf = str::from_utf8(buf).ok()?;
+    match str_buf.split_once(':') {
+        Some((params, event_type)) => {
+            let nums = parse_params(params.as_bytes())?;
+            if nums.len() != 2 {
+                return None;
+            }
+            let kind = kitty_event_kind(event_type.parse().ok()?, options)?;
+            Some((nums, kind))
+        }
+        None => Some((parse_params(buf)?, KeyEventKind::Press)),
+    }
+}
+
+/// The kind of key event of a kitty keyboard protocol event type.
+///
+/// Returns None for release events unless key event kinds are reported.
+fn kitty_event_kind(event_type: u8, options: &ParseOptions) -> Option<KeyEventKind> {
+    Some(match event_type {
+        1 => KeyEventKind::Press,
+        2 => KeyEventKind::Repeat,
+        // Releases cannot be told apart from presses without their kind.
+        3 if options.key_event_kinds => KeyEventKind::Release,
+        _ => return None,
+    })
+}
+
+/// Decodes the button code of a mouse event, as used by all mouse encodings.
+///
+/// `release` is set for SGR encoded releases, which unlike the other encodings report which
+/// button was released.
+fn parse_mouse_button(cb: u16, cx: u16, cy: u16, release: bool) -> Option<MouseEvent> {
+    let mut modifiers = Modifiers::empty();
+    if cb & 4 != 0 {
+        modifiers |= Modifiers::SHIFT;
+    }
+    if cb & 8 != 0 {
+        modifiers |= Modifiers::ALT;
+    }
+    if cb & 16 != 0 {
+        modifiers |= Modifiers::CTRL;
+    }
+
+    let wheel = cb & 64 != 0;
+    let motion = cb & 32 != 0;
+    let button = match (wheel, cb & 0b11) {
+        (false, 0) => Some(MouseButton::Left),
+        (false, 1) => Some(MouseButton::Middle),
+        (false, 2) => Some(MouseButton::Right),
+        (true, 0) => Some(MouseButton::WheelUp),
+        (true, 1) => Some(MouseButton::WheelDown),
+        (true, 2) => Some(MouseButton::WheelLeft),
+        (true, 3) => Some(MouseButton::WheelRight),
+        // Button 3 means that a button was released, without saying which one.
+        _ => None,
+    };
+
+    Some(match button {
+        _ if release => MouseEvent::Release(button, cx, cy, modifiers),
+        Some(button) if wheel => MouseEvent::Press(button, cx, cy, modifiers),
+        Some(button) if motion => MouseEvent::Hold(button, cx, cy, modifiers),
+        Some(button) => MouseEvent::Press(button, cx, cy, modifiers),
+        None if motion => MouseEvent::Moved(cx, cy, modifiers),
+        None => MouseEvent::Release(None, cx, cy, modifiers),
+    })
+}
+
+/// Reads the parameter and intermediate bytes of a CSI sequence into `buf`, up to its final byte.
+///
+/// Returns the parameters and the final byte, or None if an invalid byte is found.
+fn read_csi_params<I>(iter: &mut I, mut buf: Vec<u8>) -> Option<(Vec<u8>, u8)>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    let mut c = iter.next()?.ok()?;
+    // The final byte of a CSI sequence can be in the range 64-126, and
+    // is preceded by parameter and intermediate bytes in the range
+    // 32-63. Anything else means this is not a valid sequence.
+    while !(64..=126).contains(&c) {
+        if !(32..64).contains(&c) {
+            return None;
+        }
+        buf.push(c);
+        c = iter.next()?.ok()?;
+    }
+    Some((buf, c))
+}
+
+/// Parses the parameters of a mode report: mode ; state $
+fn parse_mode_report(buf: &[u8]) -> Option<(u16, ModeState)> {
+    let nums = parse_params(buf.strip_suffix(b"$")?)?;
+
+    if nums.len() != 2 {
+        return None;
+    }
+
+    let state = match nums[1] {
+        0 => ModeState::NotRecognized,
+        1 => ModeState::Set,
+        2 => ModeState::Reset,
+        3 => ModeState::PermanentlySet,
+        4 => ModeState::PermanentlyReset,
+        _ => return None,
+    };
+    Some((nums[0], state))
+}
+
+/// Reads the contents of an OSC or DCS string, up to its BEL or ST terminator.
+///
+/// Returns None if the string contains other control characters.
+fn read_control_string<I>(iter: &mut I) -> Option<Vec<u8>>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    let mut buf = Vec::new();
+    loop {
+        match iter.next()?.ok()? {
+            b'\x07' => return Some(buf),
+            b'\x1B' => {
+                return match iter.next()?.ok()? {
+                    b'\\' => Some(buf),
+                    _ => None,
+                }
+            }
+            c if c < 0x20 => return None,
+            c => buf.push(c),
+        }
+    }
+}
+
+/// Read the bytes after ^[] or ^[P while they may start a reply, reading at most `limit` bytes.
+///
+/// `starts_reply` tells from the bytes read so far whether they start a reply, or `None` if more
+/// bytes are needed. Returns the bytes read, as `Ok` if they start a reply. Bytes that may still
+/// start a reply when the limit is reached or the input ends do not.
+fn read_reply_start<I>(
+    iter: &mut I,
+    limit: usize,
+    starts_reply: fn(&[u8]) -> Option<bool>,
+) -> Result<Result<Vec<u8>, Vec<u8>>, Error>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    let mut buf = Vec::new();
+    loop {
+        match starts_reply(&buf) {
+            Some(true) => return Ok(Ok(buf)),
+            Some(false) => return Ok(Err(buf)),
+            None if buf.len() >= limit => return Ok(Err(buf)),
+            None => {}
+        }
+        match iter.next() {
+            Some(Ok(byte)) => buf.push(byte),
+            Some(Err(e)) => return Err(e),
+            None => return Ok(Err(buf)),
+        }
+    }
+}
+
+/// Whether an OSC sequence starts with `buf`: replies start with a number and a semicolon.
+fn starts_osc_reply(buf: &[u8]) -> Option<bool> {
+    match buf.split_last() {
+        Some((&b';', number)) => Some(!number.is_empty()),
+        Some((c, _)) if c.is_ascii_digit() => None,
+        Some(_) => Some(false),
+        None => None,
+    }
+}
+
+/// Whether a DCS sequence starts with `buf`: only the terminal version reply, starting with `>|`,
+/// is recognized.
+fn starts_dcs_reply(buf: &[u8]) -> Option<bool> {
+    match buf {
+        [] | [b'>'] => None,
+        [b'>', b'|'] => Some(true),
+        _ => Some(false),
+    }
+}
+
+/// Parses the text of an OSC sequence, read after ^[]
+///
+/// Only color replies are recognized.
+fn parse_osc(buf: &[u8]) -> Option<Event> {
+    let str_buf = str::from_utf8(buf).ok()?;
+    let mut fields = str_buf.split(';');
+
+    let slot = match fields.next()? {
+        "4" => ColorSlot::Palette(fields.next()?.parse().ok()?),
+        "10" => ColorSlot::Foreground,
+        "11" => ColorSlot::Background,
+        "12" => ColorSlot::Cursor,
+        _ => return None,
+    };
+
+    // The color is given as rgb:R/G/B, with one to four hex digits per channel.
+    let mut channels = fields.next()?.strip_prefix("rgb:")?.split('/').map(|c| {
+        let value = u32::from_str_radix(c, 16).ok()?;
+        let max = match c.len() {
+            1..=4 => (1 << (4 * c.len())) - 1,
+            _ => return None,
+        };
+        Some((value * 0xFFFF / max) as u16)
+    });
+    let (r, g, b) = (channels.next()??, channels.next()??, channels.next()??);
+
+    if channels.next().is_some() || fields.next().is_some() {
+        return None;
+    }
+
+    Some(Event::Response(Response::Color(slot, r, g, b)))
+}
+
+/// Parses the text of a DCS sequence, read after ^[P
+///
+/// Only the terminal version reply is recognized.
+fn parse_dcs(buf: &[u8]) -> Option<Event> {
+    let version = buf.strip_prefix(b">|")?;
+
+    Some(Event::Response(Response::TerminalVersion(
+        String::from_utf8_lossy(version).into_owned(),
+    )))
+}
+
+/// Parses the text of a bracketed paste, just after reading ^[[200~
+///
+/// Returns None if the input ends before the closing ^[[201~.
+fn parse_paste<I>(iter: &mut I) -> Option<Event>
+where
+    I: Iterator<Item = Result<u8, Error>>,
+{
+    const END: &[u8] = b"\x1B[201~";
+
+    let mut buf = Vec::new();
+    while !buf.ends_with(END) {
+        buf.push(iter.next()?.ok()?);
+    }
+    buf.truncate(buf.len() - END.len());
+
+    Some(Event::Paste(String::from_utf8_lossy(&buf).into_owned()))
+}
+
+/// Parses the semicolon-separated numeric parameters of a control sequence.
+///
+/// Empty parameters default to 1. Returns None if any parameter is not a number.
+fn parse_params(buf: &[u8]) -> Option<Vec<u16>> {
+    str::from_utf8(buf)
+        .ok()?
+        .split(';')
+        .map(|n| {
+            if n.is_empty() {
+                Some(1)
+            } else {
+                n.parse().ok()
+            }
+        })
+        .collect()
+}
+
 /// Parse `c` as either a single byte ASCII char or a variable size UTF-8 char.
 fn parse_utf8_char<I>(c: u8, iter: &mut I) -> Result<char, Error>
 where
@@ -449,3 +1683,151 @@ fn test_parse_utf8() {
         assert!(c == parse_utf8_char(b, bytes).unwrap());
     }
 }
+
+#[cfg(test)]
+#[test]
+fn test_key_modifiers() {
+    assert_eq!(Key::new(KeyCode::Up, Modifiers::empty()), Key::Up);
+    assert_eq!(
+        Key::new(KeyCode::Char('a'), Modifiers::CTRL),
+        Key::Ctrl('a')
+    );
+    assert_eq!(Key::new(KeyCode::Char('a'), Modifiers::ALT), Key::Alt('a'));
+
+    let key = Key::new(KeyCode::Char('a'), Modifiers::CTRL | Modifiers::SHIFT);
+    assert_eq!(
+        key,
+        Key::Modified(KeyCode::Char('a'), Modifiers::CTRL | Modifiers::SHIFT)
+    );
+    assert_eq!(key.code(), KeyCode::Char('a'));
+    assert!(key.modifiers().contains(Modifiers::CTRL));
+    assert!(!key.modifiers().contains(Modifiers::ALT));
+
+    assert_eq!(Key::Ctrl('x').code(), KeyCode::Char('x'));
+    assert_eq!(Key::Ctrl('x').modifiers(), Modifiers::CTRL);
+    assert!(Key::Left.modifiers().is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_key_notation_round_trip() {
+    let mut codes: Vec<KeyCode> = KEY_NAMES.iter().map(|&(_, code)| code).collect();
+    codes.extend(
+        [
+            'a', 'A', 'z', '1', '-', '>', 'C', 'F', 'k', 'é', '\x01', '\x7F',
+        ]
+        .iter()
+        .map(|&c| KeyCode::Char(c)),
+    );
+    codes.extend(
+        [
+            KeyCode::Null,
+            KeyCode::F(0),
+            KeyCode::F(1),
+            KeyCode::F(255),
+            KeyCode::Keypad('7'),
+            KeyCode::Keypad('>'),
+            KeyCode::BackTab,
+        ]
+        .iter()
+        .cloned(),
+    );
+
+    let mut keys = vec![
+        Key::Null,
+        Key::BackTab,
+        Key::Alt('\t'),
+        Key::Ctrl('a'),
+        Key::F(0),
+    ];
+    for &code in codes.iter() {
+        for bits in 0..64 {
+            let modifiers = Modifiers::from_bits_truncate(bits);
+            keys.push(Key::new(code, modifiers));
+            keys.push(Key::Modified(code, modifiers));
+        }
+    }
+
+    for &key in keys.iter() {
+        let notation = key.to_string();
+        assert_eq!(notation.parse(), Ok(key), "{:?} as {:?}", key, notation);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_key_notation() {
+    let keys = [
+        (Key::Char('a'), "a"),
+        (Key::Char('A'), "A"),
+        (Key::Char(' '), "<Space>"),
+        (Key::Char('<'), "<lt>"),
+        (Key::Char('\n'), "<Enter>"),
+        (Key::Char('-'), "-"),
+        (Key::Ctrl('x'), "C-x"),
+        (Key::Ctrl('-'), "C--"),
+        (Key::Alt('<'), "M-<lt>"),
+        (Key::Left, "<Left>"),
+        (Key::BackTab, "S-<Tab>"),
+        (Key::F(5), "<F5>"),
+        (Key::Keypad('7'), "<k7>"),
+        (Key::Keypad('\n'), "<kEnter>"),
+        (Key::Modified(KeyCode::Left, Modifiers::ALT), "M-<Left>"),
+        (Key::Modified(KeyCode::F(5), Modifiers::SHIFT), "S-<F5>"),
+        (
+            Key::Modified(KeyCode::Char('s'), Modifiers::CTRL | Modifiers::SUPER),
+            "C-s-s",
+        ),
+    ];
+    for &(key, notation) in keys.iter() {
+        assert_eq!(key.to_string(), notation);
+        assert_eq!(notation.parse(), Ok(key));
+    }
+
+    // Other accepted spellings.
+    assert_eq!(
+        "<S-F5>".parse(),
+        Ok(Key::Modified(KeyCode::F(5), Modifiers::SHIFT))
+    );
+    assert_eq!("<C-x>".parse(), Ok(Key::Ctrl('x')));
+    assert_eq!("<cr>".parse(), Ok(Key::Char('\n')));
+    assert_eq!("<S-Tab>".parse(), Ok(Key::BackTab));
+    assert_eq!("S-a".parse(), Ok(Key::Char('A')));
+    assert_eq!("<".parse(), Ok(Key::Char('<')));
+
+    for invalid in ["", "ab", "C-", "<Foo>", "<F256>", "<F+1>", "X-a"].iter() {
+        assert!(invalid.parse::<Key>().is_err(), "{:?}", invalid);
+    }
+
+    assert_eq!("<F0>".parse(), Ok(Key::F(0)));
+    assert_eq!(
+        Key::Modified(KeyCode::Char('A'), Modifiers::CTRL).to_string(),
+        "<Modified:C-A>"
+    );
+    assert_eq!(
+        "M-<Modified:S-Tab>".parse(),
+        Ok(Key::Modified(
+            KeyCode::Char('\t'),
+            Modifiers::ALT | Modifiers::SHIFT
+        ))
+    );
+
+    assert_eq!(
+        parse_key_sequence("C-x C-s"),
+        Ok(vec![Key::Ctrl('x'), Key::Ctrl('s')])
+    );
+    assert_eq!(
+        parse_key_sequence("<C-x><C-s> gg"),
+        Ok(vec![
+            Key::Ctrl('x'),
+            Key::Ctrl('s'),
+            Key::Char('g'),
+            Key::Char('g')
+        ])
+    );
+    assert_eq!(parse_key_sequence(""), Ok(vec![]));
+    assert_eq!(
+        parse_key_sequence("C-x <Nope>"),
+        Err(ParseKeyError("<Nope>".to_string()))
+    );
+}
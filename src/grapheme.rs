@@ -0,0 +1,180 @@
+//! Approximate grapheme cluster segmentation and display width.
+//!
+//! Termion has no Unicode tables, so this covers the common cases rather than the full rules of
+//! UAX #29 and East Asian Width: combining marks, variation selectors, emoji modifiers and ZWJ
+//! sequences, flags, and double width CJK and emoji characters.
+
+/// Zero width joiner, which joins emoji into a single cluster.
+const ZWJ: char = '\u{200D}';
+
+/// Characters extending the preceding cluster.
+const EXTEND: &[(char, char)] = &[
+    ('\u{0300}', '\u{036F}'),
+    ('\u{0483}', '\u{0489}'),
+    ('\u{0591}', '\u{05BD}'),
+    ('\u{05BF}', '\u{05C7}'),
+    ('\u{0610}', '\u{061A}'),
+    ('\u{064B}', '\u{065F}'),
+    ('\u{0670}', '\u{0670}'),
+    ('\u{06D6}', '\u{06ED}'),
+    ('\u{0900}', '\u{0903}'),
+    ('\u{093A}', '\u{094F}'),
+    ('\u{0951}', '\u{0957}'),
+    ('\u{0962}', '\u{0963}'),
+    ('\u{0E31}', '\u{0E31}'),
+    ('\u{0E34}', '\u{0E3A}'),
+    ('\u{0E47}', '\u{0E4E}'),
+    ('\u{1160}', '\u{11FF}'),
+    ('\u{1AB0}', '\u{1AFF}'),
+    ('\u{1DC0}', '\u{1DFF}'),
+    ('\u{200C}', '\u{200D}'),
+    ('\u{20D0}', '\u{20FF}'),
+    ('\u{302A}', '\u{302F}'),
+    ('\u{3099}', '\u{309A}'),
+    ('\u{FE00}', '\u{FE0F}'),
+    ('\u{FE20}', '\u{FE2F}'),
+    ('\u{1F3FB}', '\u{1F3FF}'),
+    ('\u{E0020}', '\u{E007F}'),
+    ('\u{E0100}', '\u{E01EF}'),
+];
+
+/// Characters taking two columns.
+const WIDE: &[(char, char)] = &[
+    ('\u{1100}', '\u{115F}'),
+    ('\u{231A}', '\u{231B}'),
+    ('\u{2E80}', '\u{303E}'),
+    ('\u{3041}', '\u{33FF}'),
+    ('\u{3400}', '\u{4DBF}'),
+    ('\u{4E00}', '\u{9FFF}'),
+    ('\u{A000}', '\u{A4CF}'),
+    ('\u{AC00}', '\u{D7A3}'),
+    ('\u{F900}', '\u{FAFF}'),
+    ('\u{FE30}', '\u{FE4F}'),
+    ('\u{FF00}', '\u{FF60}'),
+    ('\u{FFE0}', '\u{FFE6}'),
+    ('\u{1F1E6}', '\u{1F1FF}'),
+    ('\u{1F300}', '\u{1F64F}'),
+    ('\u{1F680}', '\u{1F6FF}'),
+    ('\u{1F900}', '\u{1F9FF}'),
+    ('\u{20000}', '\u{3FFFD}'),
+];
+
+fn in_table(table: &[(char, char)], c: char) -> bool {
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Whether `c` extends the preceding cluster.
+pub fn is_extend(c: char) -> bool {
+    in_table(EXTEND, c)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn is_pictographic(c: char) -> bool {
+    ('\u{2600}'..='\u{27BF}').contains(&c) || ('\u{1F000}'..='\u{1FAFF}').contains(&c)
+}
+
+/// The byte index of the end of the cluster starting at `pos`.
+pub fn next_boundary(s: &str, pos: usize) -> usize {
+    let mut chars = s[pos..].char_indices();
+    let mut prev = match chars.next() {
+        Some((_, c)) => c,
+        None => return pos,
+    };
+    let mut regional_indicators = is_regional_indicator(prev) as usize;
+
+    for (i, c) in chars {
+        let joined = is_extend(c)
+            || (prev == ZWJ && is_pictographic(c))
+            || (regional_indicators == 1 && is_regional_indicator(c));
+        if !joined {
+            return pos + i;
+        }
+        regional_indicators += is_regional_indicator(c) as usize;
+        prev = c;
+    }
+    s.len()
+}
+
+/// The byte index of the start of the cluster ending at `pos`.
+pub fn prev_boundary(s: &str, pos: usize) -> usize {
+    // Flags can only be told apart by counting from the start.
+    let mut start = 0;
+    loop {
+        let end = next_boundary(s, start);
+        if end >= pos {
+            return start;
+        }
+        start = end;
+    }
+}
+
+/// The number of columns taken by `s` on a terminal.
+pub fn width(s: &str) -> usize {
+    let mut width = 0;
+    let mut pos = 0;
+    while pos < s.len() {
+        let end = next_boundary(s, pos);
+        let cluster = &s[pos..end];
+        width += match cluster.chars().next() {
+            Some(c) if c.is_control() || is_extend(c) => 0,
+            Some(c) if in_table(WIDE, c) || cluster.contains('\u{FE0F}') => 2,
+            _ => 1,
+        };
+        pos = end;
+    }
+    width
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clusters(s: &str) -> Vec<&str> {
+        let mut clusters = Vec::new();
+        let mut pos = 0;
+        while pos < s.len() {
+            let end = next_boundary(s, pos);
+            clusters.push(&s[pos..end]);
+            pos = end;
+        }
+        clusters
+    }
+
+    #[test]
+    fn test_clusters() {
+        assert_eq!(clusters("abc"), ["a", "b", "c"]);
+        assert_eq!(clusters("e\u{301}x"), ["e\u{301}", "x"]);
+        assert_eq!(clusters("\u{65E5}\u{672C}"), ["\u{65E5}", "\u{672C}"]);
+        // Woman, ZWJ, laptop with a skin tone modifier.
+        let worker = "\u{1F469}\u{1F3FD}\u{200D}\u{1F4BB}";
+        assert_eq!(clusters(&format!("{}!", worker)), [worker, "!"]);
+        // Three regional indicators are a flag and a lone indicator.
+        let flags = "\u{1F1EB}\u{1F1F7}\u{1F1EB}";
+        assert_eq!(clusters(flags), ["\u{1F1EB}\u{1F1F7}", "\u{1F1EB}"]);
+        assert_eq!(prev_boundary(flags, flags.len()), 8);
+        assert_eq!(prev_boundary(flags, 8), 0);
+        assert_eq!(prev_boundary("", 0), 0);
+    }
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(width("\u{65E5}\u{672C}\u{8A9E}"), 6);
+        assert_eq!(width("\u{1F1EB}\u{1F1F7}"), 2);
+        assert_eq!(width("\u{2764}\u{FE0F}"), 2);
+    }
+}
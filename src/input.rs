@@ -1,11 +1,21 @@
 //! User input.
 
+use std::collections::VecDeque;
 use std::io::{self, Read, Write};
 use std::ops;
 use std::os::fd::AsFd;
+use std::slice;
+use std::str;
+#[cfg(all(unix, not(target_os = "redox")))]
+use std::time::{Duration, Instant};
 
-use event::{self, Event, Key};
+use event::{self, Event, Key, KeyEventKind, ParseOptions};
+use grapheme;
 use raw::IntoRawMode;
+#[cfg(all(unix, not(target_os = "redox")))]
+use raw::CONTROL_SEQUENCE_TIMEOUT;
+#[cfg(all(unix, not(target_os = "redox")))]
+use sys::poll::poll_read;
 
 /// An iterator over input keys.
 pub struct Keys<R> {
@@ -27,11 +37,60 @@ impl<R: Read> Iterator for Keys<R> {
     }
 }
 
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> Keys<R> {
+    /// Wait up to `timeout` for input, returning whether there is some to read.
+    pub fn poll(&self, timeout: Duration) -> io::Result<bool> {
+        self.iter.poll(timeout)
+    }
+
+    /// Read the next key, waiting at most `timeout` for it.
+    ///
+    /// Returns `None` if no key arrived in time. Events other than keys are skipped, without
+    /// extending the wait.
+    pub fn next_timeout(&mut self, timeout: Duration) -> io::Result<Option<Key>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.iter.next_timeout(timeout)? {
+                Some(Event::Key(k)) => return Ok(Some(k)),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
 /// An iterator over input events.
 pub struct Events<R> {
     inner: EventsAndRaw<R>,
 }
 
+impl<R> Events<R> {
+    /// Set how input is parsed, see `ParseOptions`.
+    pub fn options(self, options: ParseOptions) -> Events<R> {
+        Events {
+            inner: self.inner.options(options),
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> Events<R> {
+    /// Wait up to `timeout` for input, returning whether there is some to read.
+    pub fn poll(&self, timeout: Duration) -> io::Result<bool> {
+        self.inner.poll(timeout)
+    }
+
+    /// Read the next event, waiting at most `timeout` for it.
+    ///
+    /// Returns `None` if no input arrived in time, and an error of kind `UnexpectedEof` at the end
+    /// of the input.
+    pub fn next_timeout(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        Ok(self.inner.next_timeout(timeout)?.map(|(event, _raw)| event))
+    }
+}
+
 impl<R: Read> Iterator for Events<R> {
     type Item = Result<Event, io::Error>;
 
@@ -42,10 +101,55 @@ impl<R: Read> Iterator for Events<R> {
     }
 }
 
+/// The most bytes `EventsAndRaw` reads at once.
+const READ_SIZE: usize = 32;
+
 /// An iterator over input events and the bytes that define them.
 pub struct EventsAndRaw<R> {
     source: R,
-    leftover: Option<u8>,
+    /// Bytes read but not yet parsed.
+    leftover: VecDeque<u8>,
+    /// Whether the leftover bytes end a read that did not fill the buffer, so that no more input
+    /// was available after them.
+    drained: bool,
+    options: ParseOptions,
+}
+
+impl<R> EventsAndRaw<R> {
+    /// Set how input is parsed, see `ParseOptions`.
+    pub fn options(mut self, options: ParseOptions) -> EventsAndRaw<R> {
+        self.options = options;
+        self
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> EventsAndRaw<R> {
+    /// Wait up to `timeout` for input, returning whether there is some to read.
+    ///
+    /// Once there is, the next event can be read without waiting, unless the terminal sends only
+    /// part of an escape sequence.
+    pub fn poll(&self, timeout: Duration) -> io::Result<bool> {
+        // Bytes left over from the previous read are input as well.
+        if !self.leftover.is_empty() {
+            return Ok(true);
+        }
+        poll_read(self.source.as_fd(), Some(timeout))
+    }
+
+    /// Read the next event, waiting at most `timeout` for it.
+    ///
+    /// Returns `None` if no input arrived in time, and an error of kind `UnexpectedEof` at the end
+    /// of the input.
+    pub fn next_timeout(&mut self, timeout: Duration) -> io::Result<Option<(Event, Vec<u8>)>> {
+        if !self.poll(timeout)? {
+            return Ok(None);
+        }
+        match self.next() {
+            Some(result) => result.map(Some),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "end of input")),
+        }
+    }
 }
 
 impl<R: Read> Iterator for EventsAndRaw<R> {
@@ -53,43 +157,76 @@ impl<R: Read> Iterator for EventsAndRaw<R> {
 
     fn next(&mut self) -> Option<Result<(Event, Vec<u8>), io::Error>> {
         let source = &mut self.source;
+        let options = &self.options;
 
-        if let Some(c) = self.leftover {
-            // we have a leftover byte, use it
-            self.leftover = None;
-            return Some(parse_event(c, &mut source.bytes()));
-        }
-
-        // Here we read two bytes at a time. We need to distinguish between single ESC key presses,
-        // and escape sequences (which start with ESC or a x1B byte). The idea is that if this is
-        // an escape sequence, we will read multiple bytes (the first byte being ESC) but if this
-        // is a single ESC keypress, we will only read a single byte.
-        let mut buf = [0u8; 2];
-        let res = match source.read(&mut buf) {
-            Ok(0) => return None,
-            Ok(1) => match buf[0] {
-                b'\x1B' => Ok((Event::Key(Key::Esc), vec![b'\x1B'])),
-                c => parse_event(c, &mut source.bytes()),
-            },
-            Ok(2) => {
-                let option_iter = &mut Some(buf[1]).into_iter();
-                let result = {
-                    let mut iter = option_iter.map(|c| Ok(c)).chain(source.bytes());
-                    parse_event(buf[0], &mut iter)
-                };
-                // If the option_iter wasn't consumed, keep the byte for later.
-                self.leftover = option_iter.next();
-                result
+        if self.leftover.is_empty() {
+            // Here we read several bytes at a time. We need to distinguish between single ESC key
+            // presses, and escape sequences (which start with ESC or a x1B byte). The idea is that
+            // if this is an escape sequence, we will read multiple bytes (the first byte being
+            // ESC) but if this is a single ESC keypress, it ends the bytes read. The bytes read
+            // with an escape sequence also tell Alt+] and Alt+P from the start of a reply, without
+            // waiting for more input.
+            let mut buf = [0u8; READ_SIZE];
+            match source.read(&mut buf) {
+                Ok(0) => return None,
+                Ok(n) => {
+                    self.leftover.extend(&buf[..n]);
+                    self.drained = n < buf.len();
+                }
+                Err(e) => return Some(Err(e)),
             }
-            Ok(_) => unreachable!(),
-            Err(e) => Err(e),
+        }
+
+        let item = self.leftover.pop_front().unwrap();
+        if item == b'\x1B' && self.leftover.is_empty() && self.drained {
+            let event = options.key_event(Key::Esc, KeyEventKind::Press);
+            return Some(Ok((event, vec![b'\x1B'])));
+        }
+        let buffered = self.leftover.len();
+        let mut unread = Vec::new();
+        let result = {
+            let mut iter = LeftoverBytes {
+                leftover: &mut self.leftover,
+                source: source.bytes(),
+            };
+            parse_event(item, &mut iter, options, buffered, &mut unread)
         };
+        // Keep the bytes read past the event for later.
+        for byte in unread.into_iter().rev() {
+            self.leftover.push_front(byte);
+        }
+        Some(Ok(result))
+    }
+}
 
-        Some(res)
+/// The bytes left over from previous reads, followed by those of the source.
+struct LeftoverBytes<'a, R> {
+    leftover: &'a mut VecDeque<u8>,
+    source: io::Bytes<&'a mut R>,
+}
+
+impl<R: Read> Iterator for LeftoverBytes<'_, R> {
+    type Item = Result<u8, io::Error>;
+
+    fn next(&mut self) -> Option<Result<u8, io::Error>> {
+        match self.leftover.pop_front() {
+            Some(byte) => Some(Ok(byte)),
+            None => self.source.next(),
+        }
     }
 }
 
-fn parse_event<I>(item: u8, iter: &mut I) -> Result<(Event, Vec<u8>), io::Error>
+/// Parse an event, reporting anything unrecognized as `Event::Unsupported`.
+///
+/// Only the first `buffered` bytes of `iter` are read ahead, see `event::parse_event_with`. Bytes
+/// read past the event are added to `unread`, and left out of the returned bytes.
+fn parse_event<I>(
+    item: u8,
+    iter: &mut I,
+    options: &ParseOptions,
+    buffered: usize,
+    unread: &mut Vec<u8>,
+) -> (Event, Vec<u8>)
 where
     I: Iterator<Item = Result<u8, io::Error>>,
 {
@@ -100,11 +237,261 @@ where
                 buf.push(byte);
             }
         });
-        event::parse_event(item, &mut iter)
+        event::parse_event_with(item, &mut iter, options, buffered, unread)
     };
-    result
-        .or(Ok(Event::Unsupported(buf.clone())))
-        .map(|e| (e, buf))
+    match result {
+        Ok(event) => {
+            buf.truncate(buf.len() - unread.len());
+            (event, buf)
+        }
+        Err(_) => {
+            unread.clear();
+            (Event::Unsupported(buf.clone()), buf)
+        }
+    }
+}
+
+/// An incremental parser of input events.
+///
+/// Unlike `EventsAndRaw`, this is not tied to a `Read` source. Bytes are pushed into the parser as
+/// they arrive, in chunks of any size, and completed events are pulled out by iterating over it.
+/// Partial sequences are kept until the rest of their bytes are pushed.
+///
+/// A lone ESC byte cannot be told apart from the start of an escape sequence until more bytes
+/// arrive, so it is kept as pending as well. Call `flush_pending` once no further bytes have
+/// arrived for a while, to report it as `Key::Esc`.
+///
+/// Iteration returns `None` whenever no complete event is available, and can continue after more
+/// bytes are pushed.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{Event, Key};
+/// use termion::input::InputParser;
+///
+/// let mut parser = InputParser::new();
+/// parser.push(b"a\x1B[");
+/// assert_eq!(parser.next(), Some((Event::Key(Key::Char('a')), b"a".to_vec())));
+/// assert_eq!(parser.next(), None);
+///
+/// parser.push(b"A");
+/// assert_eq!(parser.next(), Some((Event::Key(Key::Up), b"\x1B[A".to_vec())));
+/// ```
+#[derive(Debug, Default)]
+pub struct InputParser {
+    /// Bytes that were pushed but not yet parsed.
+    pending: Vec<u8>,
+    /// Parsed events that were not yet pulled.
+    events: VecDeque<(Event, Vec<u8>)>,
+    /// How much of the pending bytes was searched for the end of the bracketed paste they start.
+    paste_searched: usize,
+    options: ParseOptions,
+}
+
+/// The start and end of a bracketed paste.
+const PASTE_START: &[u8] = b"\x1B[200~";
+const PASTE_END: &[u8] = b"\x1B[201~";
+
+impl InputParser {
+    /// Create a parser with no pending input.
+    pub fn new() -> InputParser {
+        InputParser::default()
+    }
+
+    /// Set how input is parsed, see `ParseOptions`.
+    pub fn options(mut self, options: ParseOptions) -> InputParser {
+        self.options = options;
+        self
+    }
+
+    /// Push input bytes into the parser, parsing every event they complete.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+
+        // A large paste arrives in many chunks. Only search the new bytes for its end, rather than
+        // parsing it all again on every push.
+        if self.pending.starts_with(PASTE_START) {
+            let from = self
+                .paste_searched
+                .saturating_sub(PASTE_END.len() - 1)
+                .max(PASTE_START.len());
+            let ended = self.pending[from..]
+                .windows(PASTE_END.len())
+                .any(|window| window == PASTE_END);
+            if !ended {
+                self.paste_searched = self.pending.len();
+                return;
+            }
+        }
+        self.paste_searched = 0;
+
+        let mut start = 0;
+        while start < self.pending.len() {
+            let mut iter = PendingBytes {
+                bytes: self.pending[start + 1..].iter(),
+                exhausted: false,
+            };
+            // Bytes read past the event are parsed again as the start of the next one.
+            let buffered = self.pending.len() - start - 1;
+            let (event, raw) = parse_event(
+                self.pending[start],
+                &mut iter,
+                &self.options,
+                buffered,
+                &mut Vec::new(),
+            );
+            if iter.exhausted {
+                // The sequence continues past the bytes we have.
+                break;
+            }
+
+            start += raw.len();
+            self.events.push_back((event, raw));
+        }
+        self.pending.drain(..start);
+    }
+
+    /// Whether the parser holds the start of a sequence that is not yet complete.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Give up waiting for the rest of a partial sequence.
+    ///
+    /// A lone pending ESC byte is reported as `Key::Esc`, and an ESC byte followed by a single
+    /// character, which could also be the start of a sequence, as `Key::Alt`. Any other partial
+    /// sequence is reported as `Event::Unsupported`.
+    pub fn flush_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let raw = std::mem::take(&mut self.pending);
+        self.paste_searched = 0;
+        let event = match raw.split_first() {
+            Some((&b'\x1B', [])) => self.options.key_event(Key::Esc, KeyEventKind::Press),
+            Some((&b'\x1B', rest)) => match str::from_utf8(rest).map(|s| s.chars()) {
+                Ok(mut chars) => match (chars.next(), chars.next()) {
+                    (Some(c), None) => self.options.key_event(Key::Alt(c), KeyEventKind::Press),
+                    _ => Event::Unsupported(raw.clone()),
+                },
+                Err(_) => Event::Unsupported(raw.clone()),
+            },
+            _ => Event::Unsupported(raw.clone()),
+        };
+        self.events.push_back((event, raw));
+    }
+}
+
+impl Iterator for InputParser {
+    type Item = (Event, Vec<u8>);
+
+    fn next(&mut self) -> Option<(Event, Vec<u8>)> {
+        self.events.pop_front()
+    }
+}
+
+/// The bytes following the first byte of a sequence, remembering whether the parser wanted more.
+struct PendingBytes<'a> {
+    bytes: slice::Iter<'a, u8>,
+    exhausted: bool,
+}
+
+impl Iterator for PendingBytes<'_> {
+    type Item = Result<u8, io::Error>;
+
+    fn next(&mut self) -> Option<Result<u8, io::Error>> {
+        match self.bytes.next() {
+            Some(&byte) => Some(Ok(byte)),
+            None => {
+                self.exhausted = true;
+                None
+            }
+        }
+    }
+}
+
+/// An iterator over input events, which waits a limited time for the rest of an escape sequence.
+///
+/// `Events` decides that a lone ESC byte is the Esc key when it is returned by a read on its own.
+/// Over slow links such as SSH, an escape sequence can be split across reads, producing a spurious
+/// `Key::Esc` followed by the rest of the sequence as separate keys. This iterator instead waits
+/// up to a timeout for more bytes before deciding that Esc was pressed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use termion::input::EventsWithTimeout;
+///
+/// let tty = termion::get_tty()?;
+/// for event in EventsWithTimeout::with_timeout(tty, Duration::from_millis(50)) {
+///     println!("{:?}", event?);
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(all(unix, not(target_os = "redox")))]
+pub struct EventsWithTimeout<R> {
+    source: R,
+    parser: InputParser,
+    timeout: Duration,
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> EventsWithTimeout<R> {
+    /// Read events from `source`, waiting `raw::CONTROL_SEQUENCE_TIMEOUT` milliseconds for the
+    /// rest of an escape sequence.
+    pub fn new(source: R) -> EventsWithTimeout<R> {
+        EventsWithTimeout::with_timeout(source, Duration::from_millis(CONTROL_SEQUENCE_TIMEOUT))
+    }
+
+    /// Read events from `source`, waiting `timeout` for the rest of an escape sequence.
+    pub fn with_timeout(source: R, timeout: Duration) -> EventsWithTimeout<R> {
+        EventsWithTimeout {
+            source,
+            parser: InputParser::new(),
+            timeout,
+        }
+    }
+
+    /// Set how input is parsed, see `ParseOptions`.
+    pub fn options(mut self, options: ParseOptions) -> EventsWithTimeout<R> {
+        self.parser = self.parser.options(options);
+        self
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl<R: Read + AsFd> Iterator for EventsWithTimeout<R> {
+    type Item = Result<Event, io::Error>;
+
+    fn next(&mut self) -> Option<Result<Event, io::Error>> {
+        loop {
+            if let Some((event, _raw)) = self.parser.next() {
+                return Some(Ok(event));
+            }
+
+            if self.parser.has_pending() {
+                match poll_read(self.source.as_fd(), Some(self.timeout)) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        self.parser.flush_pending();
+                        continue;
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+
+            let mut buf = [0u8; 1024];
+            match self.source.read(&mut buf) {
+                Ok(0) if self.parser.has_pending() => self.parser.flush_pending(),
+                Ok(0) => return None,
+                Ok(n) => self.parser.push(&buf[..n]),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
 }
 
 /// Extension to `Read` trait.
@@ -123,12 +510,16 @@ pub trait TermRead {
     ///
     /// EOT and ETX will abort the prompt, returning `None`. Newline or carriage return will
     /// complete the input.
+    ///
+    /// Only Backspace is supported for editing, see `editor::LineEditor` for a full line editor.
     fn read_line(&mut self) -> io::Result<Option<String>>;
 
     /// Read a password.
     ///
     /// EOT and ETX will abort the prompt, returning `None`. Newline or carriage return will
     /// complete the input.
+    ///
+    /// See `passwd::PasswordReader` for a prompt that shows masks and zeroes the password's memory.
     fn read_passwd<W: Write + AsFd>(&mut self, writer: &mut W) -> io::Result<Option<String>> {
         let _raw = writer.into_raw_mode()?;
         self.read_line()
@@ -155,7 +546,12 @@ impl<R: Read + TermReadEventsAndRaw> TermRead for R {
                 Err(e) => return Err(e),
                 Ok(0) | Ok(3) | Ok(4) => return Ok(None),
                 Ok(0x7f) => {
-                    buf.pop();
+                    // Remove the whole last character, along with any combining marks.
+                    let len = match str::from_utf8(&buf) {
+                        Ok(line) => grapheme::prev_boundary(line, line.len()),
+                        Err(_) => buf.len().saturating_sub(1),
+                    };
+                    buf.truncate(len);
                 }
                 Ok(b'\n') | Ok(b'\r') => break,
                 Ok(c) => buf.push(c),
@@ -180,78 +576,364 @@ impl<R: Read> TermReadEventsAndRaw for R {
     fn events_and_raw(self) -> EventsAndRaw<Self> {
         EventsAndRaw {
             source: self,
-            leftover: None,
+            leftover: VecDeque::new(),
+            drained: false,
+            options: ParseOptions::default(),
         }
     }
 }
 
-/// A sequence of escape codes to enable terminal mouse support.
-const ENTER_MOUSE_SEQUENCE: &'static str = csi!("?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h");
+/// The private modes controlling mouse reporting, in the order they are enabled.
+const MOUSE_MODES: [u16; 7] = [1000, 1002, 1003, 1005, 1015, 1006, 1016];
+
+/// A set of mouse reporting modes, to be enabled by a `MouseTerminal`.
+///
+/// The tracking modes select which mouse events are reported, the encodings how they are
+/// reported. When several encodings are enabled, terminals use the last one they support out of
+/// UTF-8, urxvt, SGR and SGR-pixels.
+///
+/// The default enables normal and button-event tracking, with the urxvt and SGR encodings.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::stdout;
+/// use termion::input::{MouseModes, MouseTerminal};
+///
+/// let modes = MouseModes::new().any_event().sgr();
+/// let stdout = MouseTerminal::with_modes(stdout(), modes)?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MouseModes(u8);
+
+impl MouseModes {
+    /// No modes enabled.
+    pub fn new() -> MouseModes {
+        MouseModes(0)
+    }
+
+    /// Report button presses and releases (mode 1000).
+    pub fn normal(self) -> MouseModes {
+        self.with(0)
+    }
+
+    /// Report motion while a button is held, in addition to presses and releases (mode 1002).
+    pub fn button_event(self) -> MouseModes {
+        self.with(1)
+    }
+
+    /// Report all motion, including `MouseEvent::Moved` when no button is held (mode 1003).
+    pub fn any_event(self) -> MouseModes {
+        self.with(2)
+    }
+
+    /// Encode coordinates as UTF-8 characters, allowing coordinates above 223 (mode 1005).
+    ///
+    /// This encoding cannot always be told apart from the default one, prefer `sgr`.
+    pub fn utf8(self) -> MouseModes {
+        self.with(3)
+    }
+
+    /// Encode events as decimal parameters, as done by urxvt (mode 1015).
+    pub fn urxvt(self) -> MouseModes {
+        self.with(4)
+    }
+
+    /// Encode events as decimal parameters, reporting which button was released (mode 1006).
+    pub fn sgr(self) -> MouseModes {
+        self.with(5)
+    }
+
+    /// Like `sgr`, but report coordinates in pixels instead of cells (mode 1016).
+    ///
+    /// The events look like SGR events, so the event iterator has to be told to expect pixels,
+    /// see `event::ParseOptions::pixel_mouse`.
+    pub fn sgr_pixels(self) -> MouseModes {
+        self.with(6)
+    }
+
+    fn with(self, index: usize) -> MouseModes {
+        MouseModes(self.0 | 1 << index)
+    }
+
+    /// The enabled modes, in the order they should be enabled.
+    fn modes(self) -> impl DoubleEndedIterator<Item = u16> {
+        MOUSE_MODES
+            .iter()
+            .enumerate()
+            .filter(move |&(index, _)| self.0 & 1 << index != 0)
+            .map(|(_, &mode)| mode)
+    }
+}
 
-/// A sequence of escape codes to disable terminal mouse support.
-const EXIT_MOUSE_SEQUENCE: &'static str = csi!("?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l");
+impl Default for MouseModes {
+    fn default() -> MouseModes {
+        MouseModes::new().normal().button_event().urxvt().sgr()
+    }
+}
 
 /// A terminal with added mouse support.
 ///
-/// This can be obtained through the `From` implementations.
+/// This can be obtained through the `From` implementations, which enable the default
+/// `MouseModes`, or through `MouseTerminal::with_modes`.
 pub struct MouseTerminal<W: Write> {
     term: W,
+    modes: MouseModes,
 }
 
-impl<W: Write> From<W> for MouseTerminal<W> {
-    fn from(mut from: W) -> MouseTerminal<W> {
-        from.write_all(ENTER_MOUSE_SEQUENCE.as_bytes()).unwrap();
+impl<W: Write> MouseTerminal<W> {
+    /// Enable the given mouse modes on the terminal.
+    pub fn with_modes(mut term: W, modes: MouseModes) -> io::Result<MouseTerminal<W>> {
+        for mode in modes.modes() {
+            write!(term, csi!("?{}h"), mode)?;
+        }
+
+        Ok(MouseTerminal { term, modes })
+    }
+}
 
-        MouseTerminal { term: from }
+impl<W: Write> From<W> for MouseTerminal<W> {
+    fn from(from: W) -> MouseTerminal<W> {
+        MouseTerminal::with_modes(from, MouseModes::default()).unwrap()
     }
 }
 
 impl<W: Write> Drop for MouseTerminal<W> {
     fn drop(&mut self) {
-        self.term.write_all(EXIT_MOUSE_SEQUENCE.as_bytes()).unwrap();
+        for mode in self.modes.modes().rev() {
+            write!(self.term, csi!("?{}l"), mode).unwrap();
+        }
     }
 }
 
-impl<W: Write> ops::Deref for MouseTerminal<W> {
-    type Target = W;
+impl_terminal_wrapper!(MouseTerminal);
+
+/// A sequence of escape codes to enable bracketed paste mode.
+const ENTER_BRACKETED_PASTE_SEQUENCE: &str = csi!("?2004h");
+
+/// A sequence of escape codes to disable bracketed paste mode.
+const EXIT_BRACKETED_PASTE_SEQUENCE: &str = csi!("?2004l");
 
-    fn deref(&self) -> &W {
-        &self.term
+/// A terminal with bracketed paste mode enabled.
+///
+/// Text pasted into the terminal is reported as a single `Event::Paste` instead of a key event
+/// for every character.
+///
+/// This can be obtained through the `From` implementations.
+pub struct BracketedPasteTerminal<W: Write> {
+    term: W,
+}
+
+impl<W: Write> From<W> for BracketedPasteTerminal<W> {
+    fn from(mut from: W) -> BracketedPasteTerminal<W> {
+        from.write_all(ENTER_BRACKETED_PASTE_SEQUENCE.as_bytes())
+            .unwrap();
+
+        BracketedPasteTerminal { term: from }
     }
 }
 
-impl<W: Write> ops::DerefMut for MouseTerminal<W> {
-    fn deref_mut(&mut self) -> &mut W {
-        &mut self.term
+impl<W: Write> Drop for BracketedPasteTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self
+            .term
+            .write_all(EXIT_BRACKETED_PASTE_SEQUENCE.as_bytes());
     }
 }
 
-impl<W: Write> Write for MouseTerminal<W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.term.write(buf)
+impl_terminal_wrapper!(BracketedPasteTerminal);
+
+/// A sequence of escape codes to enable application cursor key mode (DECCKM).
+const ENTER_APPLICATION_CURSOR_SEQUENCE: &str = csi!("?1h");
+
+/// A sequence of escape codes to disable application cursor key mode (DECCKM).
+const EXIT_APPLICATION_CURSOR_SEQUENCE: &str = csi!("?1l");
+
+/// A terminal with application cursor key mode enabled.
+///
+/// The cursor keys, Home and End are sent as SS3 sequences such as `ESC O A` rather than
+/// `ESC [ A`. Both forms are parsed into the same keys, but some programs expect this mode.
+///
+/// This can be obtained through the `From` implementations.
+pub struct ApplicationCursorTerminal<W: Write> {
+    term: W,
+}
+
+impl<W: Write> From<W> for ApplicationCursorTerminal<W> {
+    fn from(mut from: W) -> ApplicationCursorTerminal<W> {
+        from.write_all(ENTER_APPLICATION_CURSOR_SEQUENCE.as_bytes())
+            .unwrap();
+
+        ApplicationCursorTerminal { term: from }
     }
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.term.flush()
+impl<W: Write> Drop for ApplicationCursorTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self
+            .term
+            .write_all(EXIT_APPLICATION_CURSOR_SEQUENCE.as_bytes());
     }
 }
 
-#[cfg(unix)]
-mod unix_impl {
-    use super::*;
-    use std::os::unix::io::{AsRawFd, RawFd};
+impl_terminal_wrapper!(ApplicationCursorTerminal);
 
-    impl<W: Write + AsRawFd> AsRawFd for MouseTerminal<W> {
-        fn as_raw_fd(&self) -> RawFd {
-            self.term.as_raw_fd()
-        }
+/// A sequence of escape codes to enable application keypad mode (DECKPAM).
+const ENTER_APPLICATION_KEYPAD_SEQUENCE: &str = "\x1B=";
+
+/// A sequence of escape codes to enable numeric keypad mode (DECKPNM).
+const EXIT_APPLICATION_KEYPAD_SEQUENCE: &str = "\x1B>";
+
+/// A terminal with application keypad mode enabled.
+///
+/// The keys of the numeric keypad are reported as `Key::Keypad` instead of the characters they
+/// type.
+///
+/// This can be obtained through the `From` implementations.
+pub struct ApplicationKeypadTerminal<W: Write> {
+    term: W,
+}
+
+impl<W: Write> From<W> for ApplicationKeypadTerminal<W> {
+    fn from(mut from: W) -> ApplicationKeypadTerminal<W> {
+        from.write_all(ENTER_APPLICATION_KEYPAD_SEQUENCE.as_bytes())
+            .unwrap();
+
+        ApplicationKeypadTerminal { term: from }
     }
 }
 
+impl<W: Write> Drop for ApplicationKeypadTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self
+            .term
+            .write_all(EXIT_APPLICATION_KEYPAD_SEQUENCE.as_bytes());
+    }
+}
+
+impl_terminal_wrapper!(ApplicationKeypadTerminal);
+
+/// A sequence of escape codes to enable focus reporting.
+const ENTER_FOCUS_SEQUENCE: &str = csi!("?1004h");
+
+/// A sequence of escape codes to disable focus reporting.
+const EXIT_FOCUS_SEQUENCE: &str = csi!("?1004l");
+
+/// A terminal reporting when it gains or loses focus.
+///
+/// Focus changes are reported as `Event::FocusGained` and `Event::FocusLost`.
+///
+/// This can be obtained through the `From` implementations.
+pub struct FocusTerminal<W: Write> {
+    term: W,
+}
+
+impl<W: Write> From<W> for FocusTerminal<W> {
+    fn from(mut from: W) -> FocusTerminal<W> {
+        from.write_all(ENTER_FOCUS_SEQUENCE.as_bytes()).unwrap();
+
+        FocusTerminal { term: from }
+    }
+}
+
+impl<W: Write> Drop for FocusTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self.term.write_all(EXIT_FOCUS_SEQUENCE.as_bytes());
+    }
+}
+
+impl_terminal_wrapper!(FocusTerminal);
+
+/// Flags of the kitty keyboard protocol's progressive enhancement.
+///
+/// Flags can be combined with `|`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyboardEnhancementFlags(u8);
+
+impl KeyboardEnhancementFlags {
+    /// Report keys that are ambiguous in the legacy encoding, such as Esc or Ctrl+I, using
+    /// `CSI u` sequences.
+    pub const DISAMBIGUATE_ESCAPE_CODES: KeyboardEnhancementFlags = KeyboardEnhancementFlags(1);
+    /// Report key repeat and release events.
+    ///
+    /// These are only told apart from presses by event iterators configured with
+    /// `event::ParseOptions::key_event_kinds`. Otherwise repeats are reported as presses and
+    /// releases as `Event::Unsupported`.
+    pub const REPORT_EVENT_TYPES: KeyboardEnhancementFlags = KeyboardEnhancementFlags(2);
+    /// Report the shifted and base layout keys along with the key.
+    pub const REPORT_ALTERNATE_KEYS: KeyboardEnhancementFlags = KeyboardEnhancementFlags(4);
+    /// Report all keys, including text producing keys, using `CSI u` sequences.
+    pub const REPORT_ALL_KEYS_AS_ESCAPE_CODES: KeyboardEnhancementFlags =
+        KeyboardEnhancementFlags(8);
+    /// Report the text produced by a key along with the key.
+    pub const REPORT_ASSOCIATED_TEXT: KeyboardEnhancementFlags = KeyboardEnhancementFlags(16);
+
+    /// The raw value of the flags, as sent to the terminal.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Whether all flags in `other` are set.
+    pub fn contains(self, other: KeyboardEnhancementFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for KeyboardEnhancementFlags {
+    type Output = KeyboardEnhancementFlags;
+
+    fn bitor(self, rhs: KeyboardEnhancementFlags) -> KeyboardEnhancementFlags {
+        KeyboardEnhancementFlags(self.0 | rhs.0)
+    }
+}
+
+/// A terminal using the kitty keyboard protocol.
+///
+/// The enhancement flags are pushed onto the terminal's stack on creation, and popped again when
+/// dropped. Terminals that do not support the protocol ignore both sequences.
+///
+/// This can be obtained through the `From` implementation, which only disambiguates escape codes,
+/// or through `KeyboardEnhancementTerminal::with_flags`.
+pub struct KeyboardEnhancementTerminal<W: Write> {
+    term: W,
+}
+
+impl<W: Write> KeyboardEnhancementTerminal<W> {
+    /// Enable the given keyboard enhancement flags on the terminal.
+    pub fn with_flags(
+        mut term: W,
+        flags: KeyboardEnhancementFlags,
+    ) -> io::Result<KeyboardEnhancementTerminal<W>> {
+        write!(term, csi!(">{}u"), flags.bits())?;
+        Ok(KeyboardEnhancementTerminal { term })
+    }
+}
+
+impl<W: Write> From<W> for KeyboardEnhancementTerminal<W> {
+    fn from(from: W) -> KeyboardEnhancementTerminal<W> {
+        KeyboardEnhancementTerminal::with_flags(
+            from,
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+        )
+        .unwrap()
+    }
+}
+
+impl<W: Write> Drop for KeyboardEnhancementTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self.term.write_all(csi!("<u").as_bytes());
+    }
+}
+
+impl_terminal_wrapper!(KeyboardEnhancementTerminal);
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use event::{Event, Key, MouseButton, MouseEvent};
+    use event::{
+        ColorSlot, Event, Key, KeyCode, ModeState, Modifiers, MouseButton, MouseEvent, Response,
+    };
 
     #[test]
     fn test_keys() {
@@ -281,23 +963,43 @@ mod test {
         assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Left));
         assert_eq!(
             i.next().unwrap().unwrap(),
-            Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, 2, 4))
+            Event::Mouse(MouseEvent::Press(
+                MouseButton::WheelUp,
+                2,
+                4,
+                Modifiers::empty()
+            ))
         );
         assert_eq!(
             i.next().unwrap().unwrap(),
-            Event::Mouse(MouseEvent::Press(MouseButton::Left, 2, 4))
+            Event::Mouse(MouseEvent::Press(
+                MouseButton::Left,
+                2,
+                4,
+                Modifiers::empty()
+            ))
         );
         assert_eq!(
             i.next().unwrap().unwrap(),
-            Event::Mouse(MouseEvent::Press(MouseButton::Left, 2, 4))
+            Event::Mouse(MouseEvent::Press(
+                MouseButton::Left,
+                2,
+                4,
+                Modifiers::empty()
+            ))
         );
         assert_eq!(
             i.next().unwrap().unwrap(),
-            Event::Mouse(MouseEvent::Release(2, 4))
+            Event::Mouse(MouseEvent::Release(
+                Some(MouseButton::Left),
+                2,
+                4,
+                Modifiers::empty()
+            ))
         );
         assert_eq!(
             i.next().unwrap().unwrap(),
-            Event::Mouse(MouseEvent::Release(2, 4))
+            Event::Mouse(MouseEvent::Release(None, 2, 4, Modifiers::empty()))
         );
         assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('b')));
         assert!(i.next().is_none());
@@ -327,18 +1029,44 @@ mod test {
             assert_eq!(i.next().unwrap(), Event::Key(Key::Left));
             assert_eq!(
                 i.next().unwrap(),
-                Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, 2, 4))
+                Event::Mouse(MouseEvent::Press(
+                    MouseButton::WheelUp,
+                    2,
+                    4,
+                    Modifiers::empty()
+                ))
+            );
+            assert_eq!(
+                i.next().unwrap(),
+                Event::Mouse(MouseEvent::Press(
+                    MouseButton::Left,
+                    2,
+                    4,
+                    Modifiers::empty()
+                ))
+            );
+            assert_eq!(
+                i.next().unwrap(),
+                Event::Mouse(MouseEvent::Press(
+                    MouseButton::Left,
+                    2,
+                    4,
+                    Modifiers::empty()
+                ))
             );
             assert_eq!(
                 i.next().unwrap(),
-                Event::Mouse(MouseEvent::Press(MouseButton::Left, 2, 4))
+                Event::Mouse(MouseEvent::Release(
+                    Some(MouseButton::Left),
+                    2,
+                    4,
+                    Modifiers::empty()
+                ))
             );
             assert_eq!(
                 i.next().unwrap(),
-                Event::Mouse(MouseEvent::Press(MouseButton::Left, 2, 4))
+                Event::Mouse(MouseEvent::Release(None, 2, 4, Modifiers::empty()))
             );
-            assert_eq!(i.next().unwrap(), Event::Mouse(MouseEvent::Release(2, 4)));
-            assert_eq!(i.next().unwrap(), Event::Mouse(MouseEvent::Release(2, 4)));
             assert_eq!(i.next().unwrap(), Event::Key(Key::Char('b')));
             assert!(i.next().is_none());
         }
@@ -346,6 +1074,174 @@ mod test {
         assert_eq!(input.iter().map(|b| *b).collect::<Vec<u8>>(), output)
     }
 
+    #[test]
+    fn test_mouse_modifiers_and_drag() {
+        let mut st = b"\x1B[<4;1;2M\x1B[<18;1;2M\x1B[<34;3;4M\x1B[<33;3;4M\x1B[<2;5;6m\
+        \x1B[M\x24\x21\x22\x1B[M\x42\x21\x22\x1B[M\x23\x21\x22\x1B[64;7;8M\x1B[97;7;8M"
+            .events();
+        let mut next = || match st.next().unwrap().unwrap() {
+            Event::Mouse(me) => me,
+            e => panic!("unexpected event {:?}", e),
+        };
+        let none = Modifiers::empty();
+        assert_eq!(
+            next(),
+            MouseEvent::Press(MouseButton::Left, 1, 2, Modifiers::SHIFT)
+        );
+        assert_eq!(
+            next(),
+            MouseEvent::Press(MouseButton::Right, 1, 2, Modifiers::CTRL)
+        );
+        assert_eq!(next(), MouseEvent::Hold(MouseButton::Right, 3, 4, none));
+        assert_eq!(next(), MouseEvent::Hold(MouseButton::Middle, 3, 4, none));
+        assert_eq!(
+            next(),
+            MouseEvent::Release(Some(MouseButton::Right), 5, 6, none)
+        );
+        assert_eq!(
+            next(),
+            MouseEvent::Press(MouseButton::Left, 1, 2, Modifiers::SHIFT)
+        );
+        assert_eq!(next(), MouseEvent::Hold(MouseButton::Right, 1, 2, none));
+        assert_eq!(next(), MouseEvent::Release(None, 1, 2, none));
+        assert_eq!(next(), MouseEvent::Hold(MouseButton::Left, 7, 8, none));
+        let event = next();
+        assert_eq!(event, MouseEvent::Press(MouseButton::WheelDown, 7, 8, none));
+        assert_eq!(event.position(), (7, 8));
+        assert!(event.modifiers().is_empty());
+    }
+
+    #[test]
+    fn test_mouse_terminal() {
+        let mut out = Vec::new();
+        drop(MouseTerminal::from(&mut out));
+        assert_eq!(
+            out,
+            &b"\x1B[?1000h\x1B[?1002h\x1B[?1015h\x1B[?1006h\
+            \x1B[?1006l\x1B[?1015l\x1B[?1002l\x1B[?1000l"[..]
+        );
+
+        let mut out = Vec::new();
+        let modes = MouseModes::new().sgr().any_event();
+        drop(MouseTerminal::with_modes(&mut out, modes).unwrap());
+        assert_eq!(out, b"\x1B[?1003h\x1B[?1006h\x1B[?1006l\x1B[?1003l");
+    }
+
+    #[test]
+    fn test_mouse_moved() {
+        let mut st = b"\x1B[<35;1;2M\x1B[<43;3;4M\x1B[M\x43\x25\x26\x1B[67;7;8M\
+        \x1B[M\x20\xC3\x80\x2A"
+            .events();
+        let mut next = || match st.next().unwrap().unwrap() {
+            Event::Mouse(me) => me,
+            e => panic!("unexpected event {:?}", e),
+        };
+        let none = Modifiers::empty();
+        assert_eq!(next(), MouseEvent::Moved(1, 2, none));
+        assert_eq!(next(), MouseEvent::Moved(3, 4, Modifiers::ALT));
+        assert_eq!(next(), MouseEvent::Moved(5, 6, none));
+        assert_eq!(next(), MouseEvent::Moved(7, 8, none));
+        // UTF-8 encoded coordinates.
+        assert_eq!(next(), MouseEvent::Press(MouseButton::Left, 160, 10, none));
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_mouse_pixels() {
+        let none = Modifiers::empty();
+        let press = MouseEvent::Press(MouseButton::Left, 250, 90, none);
+        let mut st = b"\x1B[<0;250;90M".events();
+        assert_eq!(st.next().unwrap().unwrap(), Event::Mouse(press));
+
+        // Only SGR events are affected.
+        let mut st = b"\x1B[<0;250;90M\x1B[32;5;6M"
+            .events()
+            .options(ParseOptions::default().pixel_mouse(true));
+        assert_eq!(st.next().unwrap().unwrap(), Event::MousePixels(press));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, 5, 6, none))
+        );
+
+        let mut parser = InputParser::new().options(ParseOptions::default().pixel_mouse(true));
+        parser.push(b"\x1B[<0;250;90M");
+        assert_eq!(parser.next().unwrap().0, Event::MousePixels(press));
+
+        // A 80x24 terminal with 10x20 pixel cells.
+        assert_eq!(
+            press.pixels_to_cells((80, 24), (800, 480)),
+            Some(MouseEvent::Press(MouseButton::Left, 25, 5, none))
+        );
+        let corner = MouseEvent::Moved(1, 1, none);
+        assert_eq!(
+            corner.pixels_to_cells((80, 24), (800, 480)),
+            Some(MouseEvent::Moved(1, 1, none))
+        );
+        let outside = MouseEvent::Release(None, 900, 480, none);
+        assert_eq!(
+            outside.pixels_to_cells((80, 24), (800, 480)),
+            Some(MouseEvent::Release(None, 80, 24, none))
+        );
+        assert_eq!(press.pixels_to_cells((80, 24), (0, 0)), None);
+    }
+
+    #[test]
+    fn test_ss3_keys() {
+        let mut st = b"\x1BOA\x1BOB\x1BOC\x1BOD\x1BOH\x1BOF\x1BO5A\x1BO1;2P".keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::Up);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Down);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Right);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Left);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Home);
+        assert_eq!(st.next().unwrap().unwrap(), Key::End);
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Key::Modified(KeyCode::Up, Modifiers::CTRL)
+        );
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Key::Modified(KeyCode::F(1), Modifiers::SHIFT)
+        );
+        assert!(st.next().is_none());
+
+        let mut st = b"\x1BOp\x1BOy\x1BOM\x1BOX\x1BOj\x1BOk\x1BOl\x1BOm\x1BOn\x1BOo".keys();
+        for &c in &['0', '9', '\n', '=', '*', '+', ',', '-', '.', '/'] {
+            assert_eq!(st.next().unwrap().unwrap(), Key::Keypad(c));
+        }
+        assert!(st.next().is_none());
+
+        let mut st = b"\x1B[57399u\x1B[57414u\x1B[57419;5u".keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('0'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('\n'));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Key::Modified(KeyCode::Up, Modifiers::CTRL)
+        );
+        assert!(st.next().is_none());
+
+        let mut st = b"\x1BOz\x1BO2;3;4A".events();
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1BOz".to_vec())
+        );
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1BO2;3;4A".to_vec())
+        );
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_application_mode_terminals() {
+        let mut out = Vec::new();
+        drop(ApplicationCursorTerminal::from(&mut out));
+        assert_eq!(out, b"\x1B[?1h\x1B[?1l");
+
+        let mut out = Vec::new();
+        drop(ApplicationKeypadTerminal::from(&mut out));
+        assert_eq!(out, b"\x1B=\x1B>");
+    }
+
     #[test]
     fn test_function_keys() {
         let mut st = b"\x1BOP\x1BOQ\x1BOR\x1BOS".keys();
@@ -359,6 +1255,50 @@ mod test {
         for i in 1..13 {
             assert_eq!(st.next().unwrap().unwrap(), Key::F(i));
         }
+
+        let mut st = b"\x1B[25~\x1B[26~\x1B[28~\x1B[29~\x1B[31~\x1B[32~\x1B[33~\x1B[34~".keys();
+        for i in 13..21 {
+            assert_eq!(st.next().unwrap().unwrap(), Key::F(i));
+        }
+        assert!(st.next().is_none());
+
+        let mut st = b"\x1B[57376u\x1B[57398u\x1B[34;2~\x1B[24;5~".keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::F(13));
+        assert_eq!(st.next().unwrap().unwrap(), Key::F(35));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Key::Modified(KeyCode::F(20), Modifiers::SHIFT)
+        );
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Key::Modified(KeyCode::F(12), Modifiers::CTRL)
+        );
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_rxvt_modified_keys() {
+        let mut st = b"\x1B[2$x\x1B[11^\x1B[3@\x1B[a\x1BOd\x1B[2;1$y".events();
+        let mut next = || match st.next().unwrap().unwrap() {
+            Event::Key(k) => (k.code(), k.modifiers()),
+            e => panic!("unexpected event {:?}", e),
+        };
+        assert_eq!(next(), (KeyCode::Insert, Modifiers::SHIFT));
+        // The byte after a $ terminated key is not part of it.
+        assert_eq!(next(), (KeyCode::Char('x'), Modifiers::empty()));
+        assert_eq!(next(), (KeyCode::F(1), Modifiers::CTRL));
+        assert_eq!(
+            next(),
+            (KeyCode::Delete, Modifiers::CTRL | Modifiers::SHIFT)
+        );
+        assert_eq!(next(), (KeyCode::Up, Modifiers::SHIFT));
+        assert_eq!(next(), (KeyCode::Left, Modifiers::CTRL));
+        // Mode reports also use $, after two parameters.
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Response(Response::Mode(2, ModeState::Set))
+        );
+        assert!(st.next().is_none());
     }
 
     #[test]
@@ -375,6 +1315,418 @@ mod test {
         assert!(st.next().is_none());
     }
 
+    #[test]
+    fn test_modified_keys() {
+        let mut st = b"\x1B[1;2D\x1B[1;3A\x1B[1;5H\x1B[1;6B\x1B[3;2~\x1B[15;3~\x1B[5;5~\
+        \x1B[1;5P\x1B[1;1C\x1B[3;2;1~"
+            .events();
+        let mut next = || match st.next().unwrap().unwrap() {
+            Event::Key(k) => (k.code(), k.modifiers()),
+            e => panic!("unexpected event {:?}", e),
+        };
+        assert_eq!(next(), (KeyCode::Left, Modifiers::SHIFT));
+        assert_eq!(next(), (KeyCode::Up, Modifiers::ALT));
+        assert_eq!(next(), (KeyCode::Home, Modifiers::CTRL));
+        assert_eq!(next(), (KeyCode::Down, Modifiers::CTRL | Modifiers::SHIFT));
+        assert_eq!(next(), (KeyCode::Delete, Modifiers::SHIFT));
+        assert_eq!(next(), (KeyCode::F(5), Modifiers::ALT));
+        assert_eq!(next(), (KeyCode::PageUp, Modifiers::CTRL));
+        assert_eq!(next(), (KeyCode::F(1), Modifiers::CTRL));
+        assert_eq!(next(), (KeyCode::Right, Modifiers::empty()));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1B[3;2;1~".to_vec())
+        );
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_paste() {
+        let input = b"a\x1B[200~hello\x1B[A\r\nw\xC3\xB6rld\x1B[201~b\x1B[200~cut";
+        let mut st = input.events_and_raw().map(|e| e.unwrap());
+        assert_eq!(st.next().unwrap().0, Event::Key(Key::Char('a')));
+        assert_eq!(
+            st.next().unwrap(),
+            (
+                Event::Paste("hello\x1B[A\r\nwörld".to_string()),
+                input[1..29].to_vec()
+            )
+        );
+        assert_eq!(st.next().unwrap().0, Event::Key(Key::Char('b')));
+        assert_eq!(
+            st.next().unwrap().0,
+            Event::Unsupported(b"\x1B[200~cut".to_vec())
+        );
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_bracketed_paste_terminal() {
+        let mut out = Vec::new();
+        drop(BracketedPasteTerminal::from(&mut out));
+        assert_eq!(out, b"\x1B[?2004h\x1B[?2004l");
+    }
+
+    #[test]
+    fn test_focus() {
+        let mut st = b"\x1B[Ia\x1B[O".events();
+        assert_eq!(st.next().unwrap().unwrap(), Event::FocusGained);
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::FocusLost);
+        assert!(st.next().is_none());
+
+        let mut out = Vec::new();
+        drop(FocusTerminal::from(&mut out));
+        assert_eq!(out, b"\x1B[?1004h\x1B[?1004l");
+    }
+
+    #[test]
+    fn test_malformed_sequences() {
+        let inputs: &[&[u8]] = &[
+            b"\x1B[M",
+            b"\x1B[M\x00",
+            b"\x1B[M\xFF\xFF\xFF",
+            b"\x1B[<",
+            b"\x1B[<0;1",
+            b"\x1B[<0;1;M",
+            b"\x1B[<;;M",
+            b"\x1B[<99999;1;1M",
+            b"\x1B[<0;\xFF;1M",
+            b"\x1B[1",
+            b"\x1B[1;",
+            b"\x1B[32;1M",
+            b"\x1B[32;1;2;3;4M",
+            b"\x1B[1\x00~",
+            b"\x1B[99999999~",
+            b"\x1B[3;99999~",
+            b"\x1B[1;0A",
+            b"\x1B[97;0u",
+            b"\x1B[1114112u",
+            b"\x1B[55296u",
+            b"\x1B[200~unterminated",
+            b"\x1BO",
+            b"\x1B\xC3",
+            b"\xF0\x9F",
+        ];
+
+        for input in inputs {
+            let mut output = Vec::new();
+            for event in input.events_and_raw() {
+                output.extend(event.unwrap().1);
+            }
+            assert_eq!(&output[..], *input);
+        }
+
+        let mut st = b"\x1B[Ma".events();
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1B[Ma".to_vec())
+        );
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_input_parser() {
+        let mut parser = InputParser::new();
+        let mut events = Vec::new();
+        for chunk in [
+            &b"a\x1B"[..],
+            b"[",
+            b"1;5",
+            b"A\xC3",
+            b"\xA9\x1B[200~pas",
+            b"ted\x1B[20",
+            b"1~\x1B",
+        ] {
+            parser.push(chunk);
+            events.extend(&mut parser);
+        }
+        assert_eq!(
+            events,
+            vec![
+                (Event::Key(Key::Char('a')), b"a".to_vec()),
+                (
+                    Event::Key(Key::Modified(KeyCode::Up, Modifiers::CTRL)),
+                    b"\x1B[1;5A".to_vec()
+                ),
+                (Event::Key(Key::Char('é')), b"\xC3\xA9".to_vec()),
+                (
+                    Event::Paste("pasted".to_string()),
+                    b"\x1B[200~pasted\x1B[201~".to_vec()
+                ),
+            ]
+        );
+
+        assert!(parser.has_pending());
+        parser.flush_pending();
+        assert!(!parser.has_pending());
+        assert_eq!(parser.next(), Some((Event::Key(Key::Esc), vec![0x1B])));
+
+        parser.push(b"\x1B[\x00b\x1B[<0;1");
+        assert_eq!(
+            parser.next(),
+            Some((
+                Event::Unsupported(vec![0x1B, b'[', 0x00]),
+                vec![0x1B, b'[', 0x00]
+            ))
+        );
+        assert_eq!(
+            parser.next(),
+            Some((Event::Key(Key::Char('b')), b"b".to_vec()))
+        );
+        assert_eq!(parser.next(), None);
+        parser.flush_pending();
+        assert_eq!(
+            parser.next(),
+            Some((
+                Event::Unsupported(b"\x1B[<0;1".to_vec()),
+                b"\x1B[<0;1".to_vec()
+            ))
+        );
+        assert_eq!(parser.next(), None);
+    }
+
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[test]
+    fn test_events_with_timeout() {
+        use std::os::unix::net::UnixStream;
+        use std::thread;
+        use std::time::Duration;
+
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let mut events = EventsWithTimeout::with_timeout(rx, Duration::from_millis(500));
+
+        // A sequence split across writes is still a single key.
+        let writer = thread::spawn(move || {
+            tx.write_all(b"\x1B").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            tx.write_all(b"[A").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            tx.write_all(b"\x1B").unwrap();
+            tx
+        });
+        assert_eq!(events.next().unwrap().unwrap(), Event::Key(Key::Up));
+
+        // A lone ESC is the Esc key once the timeout elapses.
+        assert_eq!(events.next().unwrap().unwrap(), Event::Key(Key::Esc));
+
+        drop(writer.join().unwrap());
+        assert!(events.next().is_none());
+    }
+
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[test]
+    fn test_next_timeout() {
+        use std::os::unix::net::UnixStream;
+        use std::time::{Duration, Instant};
+
+        let tick = Duration::from_millis(20);
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let mut events = rx.events();
+
+        let start = Instant::now();
+        assert!(!events.poll(tick).unwrap());
+        assert_eq!(events.next_timeout(tick).unwrap(), None);
+        assert!(start.elapsed() >= tick * 2);
+
+        // The second byte is left over from reading the first, and still counts as input.
+        tx.write_all(b"ab").unwrap();
+        assert_eq!(
+            events.next_timeout(tick).unwrap(),
+            Some(Event::Key(Key::Char('a')))
+        );
+        assert!(events.poll(Duration::from_secs(0)).unwrap());
+        assert_eq!(
+            events.next_timeout(tick).unwrap(),
+            Some(Event::Key(Key::Char('b')))
+        );
+
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let mut keys = rx.keys();
+        tx.write_all(b"\x1B[Ix").unwrap();
+        assert_eq!(keys.next_timeout(tick).unwrap(), Some(Key::Char('x')));
+        assert_eq!(keys.next_timeout(tick).unwrap(), None);
+
+        drop(tx);
+        let error = keys.next_timeout(tick).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_responses() {
+        let mut st = b"\x1B[12;40R\x1B[?62;22;52c\x1B[>41;367;0c\x1B[?2004;1$y\x1B[4;2$y\
+        \x1B]11;rgb:ffff/8080/0000\x1B\\\x1B]4;1;rgb:f/80/123\x07\x1BP>|xterm(367)\x1B\\\
+        \x1B[1;5R\x1B]52;c;?\x07"
+            .events();
+        let mut next = || match st.next().unwrap().unwrap() {
+            Event::Response(r) => r,
+            e => panic!("unexpected event {:?}", e),
+        };
+        assert_eq!(next(), Response::CursorPosition(40, 12));
+        assert_eq!(next(), Response::PrimaryDeviceAttributes(vec![62, 22, 52]));
+        assert_eq!(
+            next(),
+            Response::SecondaryDeviceAttributes(vec![41, 367, 0])
+        );
+        assert_eq!(next(), Response::PrivateMode(2004, ModeState::Set));
+        assert_eq!(next(), Response::Mode(4, ModeState::Reset));
+        assert_eq!(
+            next(),
+            Response::Color(ColorSlot::Background, 0xFFFF, 0x8080, 0)
+        );
+        assert_eq!(
+            next(),
+            Response::Color(ColorSlot::Palette(1), 0xFFFF, 0x8080, 0x1231)
+        );
+        assert_eq!(next(), Response::TerminalVersion("xterm(367)".to_string()));
+        assert_eq!(next(), Response::CursorPosition(5, 1));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1B]52;c;?\x07".to_vec())
+        );
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_alt_keys_starting_replies() {
+        // ESC ] and ESC P only start replies when a reply follows.
+        let input = b"\x1BPx\x1BPy\x1B]a\rb\x1B]1x\x1BP>";
+        let events: Vec<(Event, Vec<u8>)> = input.events_and_raw().map(Result::unwrap).collect();
+        let expected: Vec<(Event, &[u8])> = vec![
+            (Event::Key(Key::Alt('P')), b"\x1BP"),
+            (Event::Key(Key::Char('x')), b"x"),
+            (Event::Key(Key::Alt('P')), b"\x1BP"),
+            (Event::Key(Key::Char('y')), b"y"),
+            (Event::Key(Key::Alt(']')), b"\x1B]"),
+            (Event::Key(Key::Char('a')), b"a"),
+            (Event::Key(Key::Char('\n')), b"\r"),
+            (Event::Key(Key::Char('b')), b"b"),
+            (Event::Key(Key::Alt(']')), b"\x1B]"),
+            (Event::Key(Key::Char('1')), b"1"),
+            (Event::Key(Key::Char('x')), b"x"),
+            (Event::Key(Key::Alt('P')), b"\x1BP"),
+            (Event::Key(Key::Char('>')), b">"),
+        ];
+        let expected: Vec<(Event, Vec<u8>)> = expected
+            .into_iter()
+            .map(|(event, raw)| (event, raw.to_vec()))
+            .collect();
+        assert_eq!(events, expected);
+
+        let mut parser = InputParser::new();
+        parser.push(input);
+        let parsed: Vec<(Event, Vec<u8>)> = parser.collect();
+        assert_eq!(parsed, expected);
+
+        // Only the bytes already pushed are looked at, so keys typed later are never taken for a
+        // reply.
+        let mut parser = InputParser::new();
+        for chunk in [&b"\x1B]"[..], b"1;", b"\x1BP", b">|"] {
+            parser.push(chunk);
+        }
+        let parsed: Vec<Event> = parser.map(|(event, _)| event).collect();
+        assert_eq!(
+            parsed,
+            [
+                Event::Key(Key::Alt(']')),
+                Event::Key(Key::Char('1')),
+                Event::Key(Key::Char(';')),
+                Event::Key(Key::Alt('P')),
+                Event::Key(Key::Char('>')),
+                Event::Key(Key::Char('|')),
+            ]
+        );
+    }
+
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[test]
+    fn test_alt_keys_blocking_source() {
+        use std::os::unix::net::UnixStream;
+        use std::sync::mpsc;
+        use std::thread;
+
+        // The keys are reported without waiting for the input after them.
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let (send, recv) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            for key in rx.keys() {
+                if send.send(key.unwrap()).is_err() {
+                    return;
+                }
+            }
+        });
+        let timeout = Duration::from_millis(500);
+        tx.write_all(b"\x1B]").unwrap();
+        assert_eq!(recv.recv_timeout(timeout), Ok(Key::Alt(']')));
+        tx.write_all(b"1;").unwrap();
+        assert_eq!(recv.recv_timeout(timeout), Ok(Key::Char('1')));
+        assert_eq!(recv.recv_timeout(timeout), Ok(Key::Char(';')));
+        tx.write_all(b"\x1BP").unwrap();
+        assert_eq!(recv.recv_timeout(timeout), Ok(Key::Alt('P')));
+        tx.write_all(b">|").unwrap();
+        assert_eq!(recv.recv_timeout(timeout), Ok(Key::Char('>')));
+        assert_eq!(recv.recv_timeout(timeout), Ok(Key::Char('|')));
+
+        drop(tx);
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn test_input_parser_paste_chunks() {
+        let mut parser = InputParser::new();
+        let text = "x".repeat(10000);
+        let input = format!("a\x1B[200~{}\x1B[201~b", text);
+        for chunk in input.as_bytes().chunks(7) {
+            parser.push(chunk);
+        }
+        let events: Vec<Event> = parser.map(|(event, _)| event).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::Key(Key::Char('a')),
+                Event::Paste(text),
+                Event::Key(Key::Char('b')),
+            ]
+        );
+
+        // The end of a paste may be split across pushes.
+        let mut parser = InputParser::new();
+        for chunk in [&b"\x1B[200~ab\x1B"[..], b"[20", b"1", b"~"] {
+            assert_eq!(parser.next(), None);
+            parser.push(chunk);
+        }
+        assert_eq!(
+            parser.next().map(|(e, _)| e),
+            Some(Event::Paste("ab".into()))
+        );
+    }
+
+    #[test]
+    fn test_input_parser_flush_alt() {
+        let mut parser = InputParser::new();
+        for pending in [
+            &b"\x1B]"[..],
+            b"\x1BP",
+            b"\x1B[",
+            b"\x1B\xC3\xA9",
+            b"\x1B[1",
+        ] {
+            parser.push(pending);
+            parser.flush_pending();
+        }
+        let events: Vec<Event> = parser.map(|(event, _)| event).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::Key(Key::Alt(']')),
+                Event::Key(Key::Alt('P')),
+                Event::Key(Key::Alt('[')),
+                Event::Key(Key::Alt('é')),
+                Event::Unsupported(b"\x1B[1".to_vec()),
+            ]
+        );
+    }
+
     #[test]
     fn test_esc_key() {
         let mut st = b"\x1B".keys();
@@ -382,6 +1734,103 @@ mod test {
         assert!(st.next().is_none());
     }
 
+    #[test]
+    fn test_kitty_keys() {
+        let mut st = b"\x1B[27u\x1B[97u\x1B[97;2u\x1B[97:65;2u\x1B[97;3u\x1B[99;5u\
+        \x1B[13u\x1B[9;2u\x1B[127;1:2u\x1B[97;1:3u\x1B[97;7u\x1B[P\x1B[Q\x1B[S"
+            .events();
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Esc));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Char('A')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Char('A')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Alt('a')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Ctrl('c')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Char('\n')));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::BackTab));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Backspace));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1B[97;1:3u".to_vec())
+        );
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Key(Key::Modified(
+                KeyCode::Char('a'),
+                Modifiers::ALT | Modifiers::CTRL
+            ))
+        );
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::F(1)));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::F(2)));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::F(4)));
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_key_event_kinds() {
+        use event::KeyEventKind::*;
+
+        let mut st = b"a\x1B[97;1:1u\x1B[97;1:2u\x1B[97;5:3u\x1B[A\x1B"
+            .events()
+            .options(ParseOptions::default().key_event_kinds(true));
+        let mut next = || st.next().unwrap().unwrap();
+        assert_eq!(next(), Event::KeyEvent(Key::Char('a'), Press));
+        assert_eq!(next(), Event::KeyEvent(Key::Char('a'), Press));
+        assert_eq!(next(), Event::KeyEvent(Key::Char('a'), Repeat));
+        assert_eq!(next(), Event::KeyEvent(Key::Ctrl('a'), Release));
+        assert_eq!(next(), Event::KeyEvent(Key::Up, Press));
+        assert_eq!(next(), Event::KeyEvent(Key::Esc, Press));
+        assert!(st.next().is_none());
+
+        let mut parser = InputParser::new().options(ParseOptions::default().key_event_kinds(true));
+        parser.push(b"\x1B[27;1:3u\x1B");
+        parser.flush_pending();
+        assert_eq!(parser.next().unwrap().0, Event::KeyEvent(Key::Esc, Release));
+        assert_eq!(parser.next().unwrap().0, Event::KeyEvent(Key::Esc, Press));
+        assert!(parser.next().is_none());
+
+        // Without the option, repeats are presses.
+        let mut st = b"\x1B[97;1:2u".events();
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+
+        // Keys keeping their legacy encoding carry the event type as well.
+        let mut st = b"\x1B[1;1:2A\x1B[1;5:3D\x1B[1;1:3P\x1B[15;1:2~\x1B[3;3:3~\x1B[5;1:1~"
+            .events()
+            .options(ParseOptions::default().key_event_kinds(true));
+        let mut next = || st.next().unwrap().unwrap();
+        assert_eq!(next(), Event::KeyEvent(Key::Up, Repeat));
+        assert_eq!(
+            next(),
+            Event::KeyEvent(Key::Modified(KeyCode::Left, Modifiers::CTRL), Release)
+        );
+        assert_eq!(next(), Event::KeyEvent(Key::F(1), Release));
+        assert_eq!(next(), Event::KeyEvent(Key::F(5), Repeat));
+        assert_eq!(
+            next(),
+            Event::KeyEvent(Key::Modified(KeyCode::Delete, Modifiers::ALT), Release)
+        );
+        assert_eq!(next(), Event::KeyEvent(Key::PageUp, Press));
+        assert!(st.next().is_none());
+
+        let mut st = b"\x1B[1;1:2B\x1B[15;1:3~".events();
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Down));
+        assert_eq!(
+            st.next().unwrap().unwrap(),
+            Event::Unsupported(b"\x1B[15;1:3~".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_keyboard_enhancement_terminal() {
+        let mut out = Vec::new();
+        {
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS;
+            let mut term = KeyboardEnhancementTerminal::with_flags(&mut out, flags).unwrap();
+            term.write_all(b"x").unwrap();
+        }
+        assert_eq!(out, b"\x1B[>5ux\x1B[<u");
+    }
+
     fn line_match(a: &str, b: Option<&str>) {
         let line = a.as_bytes().read_line().unwrap();
         let pass = a.as_bytes().read_passwd(&mut std::io::stdout()).unwrap();
@@ -418,6 +1867,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_backspace_utf8() {
+        let read = |s: &str| s.as_bytes().read_line().unwrap().unwrap();
+        assert_eq!(read("caf\u{e9}\x7f\n"), "caf");
+        // Combining marks are removed with their letter.
+        assert_eq!(read("cafe\u{301}\x7f\n"), "caf");
+        assert_eq!(read("\u{65E5}\u{672C}\u{8A9E}\x7f\x7f!\n"), "\u{65E5}!");
+        assert_eq!(read("\u{D55C}\u{1100}\u{1161}\u{11AB}\x7f\n"), "\u{D55C}");
+        assert_eq!(read("ok \u{1F44D}\u{1F3FD}\x7f\n"), "ok ");
+        assert_eq!(read("\x7f\x7fa\n"), "a");
+    }
+
     #[test]
     fn test_end() {
         line_match(
@@ -0,0 +1,377 @@
+//! Matching key sequences against bindings.
+//!
+//! A `Keymap` maps sequences of keys, such as `C-x C-s`, to actions. Keys are fed to it one at a
+//! time as they are read, and it reports when a sequence was completed.
+//!
+//! A sequence that is bound and also starts longer bindings is ambiguous: after `g`, with both `g`
+//! and `g g` bound, it is not known yet whether `g` alone was meant. Such sequences are resolved
+//! when the next key does not continue a longer binding, or once no key has been fed for the
+//! keymap's timeout.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::io::{stdout, Write};
+//! use termion::event::{parse_key_sequence, Event};
+//! use termion::input::TermRead;
+//! use termion::keymap::{Keymap, KeymapResult};
+//! use termion::raw::IntoRawMode;
+//!
+//! let mut keymap = Keymap::new();
+//! keymap.bind(&parse_key_sequence("g").unwrap(), "next");
+//! keymap.bind(&parse_key_sequence("g g").unwrap(), "top");
+//! keymap.bind(&parse_key_sequence("C-x C-c").unwrap(), "quit");
+//!
+//! let mut stdout = stdout().into_raw_mode()?;
+//! let mut events = termion::get_tty()?.events();
+//! loop {
+//!     // Wait for the rest of an ambiguous sequence, like `g g`, only until the keymap's timeout.
+//!     let event = match keymap.timeout() {
+//!         Some(timeout) => events.next_timeout(timeout)?,
+//!         None => match events.next() {
+//!             Some(event) => Some(event?),
+//!             None => break,
+//!         },
+//!     };
+//!     let mut result = match event {
+//!         Some(Event::Key(key)) => keymap.feed(key),
+//!         Some(_) => continue,
+//!         None => keymap.expire(),
+//!     };
+//!     loop {
+//!         match result {
+//!             KeymapResult::Pending => {}
+//!             KeymapResult::Matched("quit") => return Ok(()),
+//!             KeymapResult::Matched(action) => write!(stdout, "{}\r\n", action)?,
+//!             KeymapResult::NoMatch(keys) => write!(stdout, "unbound: {:?}\r\n", keys)?,
+//!         }
+//!         // A key can resolve more than one sequence.
+//!         if !keymap.has_queued() {
+//!             break;
+//!         }
+//!         result = keymap.expire();
+//!     }
+//! }
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use event::Key;
+
+/// How long to wait for the rest of an ambiguous key sequence by default, in milliseconds.
+pub const DEFAULT_TIMEOUT: u64 = 1000;
+
+/// The outcome of feeding a key to a `Keymap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapResult<A> {
+    /// The keys fed so far may still complete a binding.
+    Pending,
+    /// A bound sequence was completed, giving its action.
+    Matched(A),
+    /// The keys do not start any binding.
+    ///
+    /// Only the keys that cannot be part of a binding are given: any following keys that may
+    /// start another binding are matched again.
+    NoMatch(Vec<Key>),
+}
+
+/// A set of key sequences bound to actions.
+///
+/// See the module documentation.
+#[derive(Debug)]
+pub struct Keymap<A> {
+    /// The bindings, as a trie of keys.
+    root: Node<A>,
+    /// How long to wait for the rest of an ambiguous sequence.
+    timeout: Duration,
+    /// The keys fed so far, which start one or more bindings.
+    keys: Vec<Key>,
+    /// Keys fed but not matched yet, because an earlier sequence was resolved first.
+    queued: VecDeque<Key>,
+    /// When the last key was fed.
+    last_fed: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Node<A> {
+    action: Option<A>,
+    children: HashMap<Key, Node<A>>,
+}
+
+impl<A> Node<A> {
+    fn new() -> Node<A> {
+        Node {
+            action: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+impl<A: Clone> Keymap<A> {
+    /// Create an empty keymap, waiting `DEFAULT_TIMEOUT` milliseconds for ambiguous sequences.
+    pub fn new() -> Keymap<A> {
+        Keymap::with_timeout(Duration::from_millis(DEFAULT_TIMEOUT))
+    }
+
+    /// Create an empty keymap, waiting `timeout` for the rest of ambiguous sequences.
+    pub fn with_timeout(timeout: Duration) -> Keymap<A> {
+        Keymap {
+            root: Node::new(),
+            timeout,
+            keys: Vec::new(),
+            queued: VecDeque::new(),
+            last_fed: None,
+        }
+    }
+
+    /// Bind a key sequence to an action, returning the action it was bound to before.
+    ///
+    /// The empty sequence is never matched.
+    pub fn bind(&mut self, keys: &[Key], action: A) -> Option<A> {
+        let mut node = &mut self.root;
+        for &key in keys {
+            node = node.children.entry(key).or_insert_with(Node::new);
+        }
+        node.action.replace(action)
+    }
+
+    /// The action bound to a key sequence.
+    pub fn get(&self, keys: &[Key]) -> Option<&A> {
+        self.node(keys).and_then(|node| node.action.as_ref())
+    }
+
+    /// Feed the next key.
+    ///
+    /// Keys are reported as they complete bindings. A key can complete more than one result, for
+    /// example when it shows that an earlier ambiguous sequence was meant on its own. The later
+    /// results are then returned by `expire`, while `has_queued` is true.
+    pub fn feed(&mut self, key: Key) -> KeymapResult<A> {
+        self.last_fed = Some(Instant::now());
+        self.queued.push_back(key);
+        self.match_queued()
+    }
+
+    /// How long to wait for another key before calling `expire`, to resolve an ambiguous sequence.
+    ///
+    /// Returns `None` if no keys are waiting for more, in which case there is no need to wait.
+    /// Results queued by `feed` are not waited for, see `has_queued`.
+    pub fn timeout(&self) -> Option<Duration> {
+        if self.keys.is_empty() {
+            return None;
+        }
+        let elapsed = self
+            .last_fed
+            .map_or(self.timeout, |last_fed| last_fed.elapsed());
+        Some(self.timeout.checked_sub(elapsed).unwrap_or_default())
+    }
+
+    /// Give up waiting for more keys, resolving the pending ones.
+    ///
+    /// The longest bound sequence among the pending keys is matched. Call this once `timeout` has
+    /// passed without a key being fed.
+    pub fn expire(&mut self) -> KeymapResult<A> {
+        if self.keys.is_empty() {
+            return self.match_queued();
+        }
+        let len = self.keys.len();
+        self.resolve(len)
+    }
+
+    /// Whether keys were fed that are still to be matched, giving further results.
+    ///
+    /// Call `expire` for each of them, without waiting.
+    pub fn has_queued(&self) -> bool {
+        !self.queued.is_empty()
+    }
+
+    /// Whether keys were fed that are not resolved yet.
+    pub fn is_pending(&self) -> bool {
+        !self.keys.is_empty() || !self.queued.is_empty()
+    }
+
+    /// Forget the pending keys.
+    pub fn clear_pending(&mut self) {
+        self.keys.clear();
+        self.queued.clear();
+    }
+
+    fn node(&self, keys: &[Key]) -> Option<&Node<A>> {
+        keys.iter()
+            .try_fold(&self.root, |node, key| node.children.get(key))
+    }
+
+    /// Match the queued keys until a result other than `Pending` is found.
+    fn match_queued(&mut self) -> KeymapResult<A> {
+        while let Some(key) = self.queued.pop_front() {
+            self.keys.push(key);
+            let action = match self.node(&self.keys) {
+                // The sequence may continue.
+                Some(node) if !node.children.is_empty() => continue,
+                Some(node) => node.action.clone(),
+                None => None,
+            };
+            return match action {
+                Some(action) => {
+                    self.keys.clear();
+                    KeymapResult::Matched(action)
+                }
+                // The last key does not continue the sequence.
+                None => {
+                    let len = self.keys.len() - 1;
+                    self.resolve(len)
+                }
+            };
+        }
+        KeymapResult::Pending
+    }
+
+    /// Resolve the pending keys to their longest bound sequence of at most `len` keys, queueing the
+    /// keys after it to be matched again.
+    fn resolve(&mut self, len: usize) -> KeymapResult<A> {
+        let matched = (1..=len)
+            .rev()
+            .filter_map(|len| Some((len, self.get(&self.keys[..len])?.clone())))
+            .next();
+        let (len, result) = match matched {
+            Some((len, action)) => (len, KeymapResult::Matched(action)),
+            None => (1, KeymapResult::NoMatch(self.keys[..1].to_vec())),
+        };
+
+        for &key in self.keys[len..].iter().rev() {
+            self.queued.push_front(key);
+        }
+        self.keys.clear();
+        result
+    }
+}
+
+impl<A: Clone> Default for Keymap<A> {
+    fn default() -> Keymap<A> {
+        Keymap::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::parse_key_sequence;
+
+    fn keymap() -> Keymap<&'static str> {
+        let mut keymap = Keymap::new();
+        for &(keys, action) in [
+            ("C-x C-s", "save"),
+            ("C-x C-c", "quit"),
+            ("g", "go"),
+            ("g g", "top"),
+            ("j k", "escape"),
+        ]
+        .iter()
+        {
+            keymap.bind(&parse_key_sequence(keys).unwrap(), action);
+        }
+        keymap
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut keymap = keymap();
+        assert_eq!(keymap.feed(Key::Ctrl('x')), KeymapResult::Pending);
+        assert!(keymap.timeout().is_some());
+        assert_eq!(keymap.feed(Key::Ctrl('s')), KeymapResult::Matched("save"));
+        assert_eq!(keymap.timeout(), None);
+
+        assert_eq!(
+            keymap.feed(Key::Char('a')),
+            KeymapResult::NoMatch(vec![Key::Char('a')])
+        );
+        assert_eq!(keymap.feed(Key::Ctrl('x')), KeymapResult::Pending);
+        assert_eq!(
+            keymap.feed(Key::Char('a')),
+            KeymapResult::NoMatch(vec![Key::Ctrl('x')])
+        );
+        assert!(keymap.has_queued());
+        assert_eq!(keymap.timeout(), None);
+        assert_eq!(keymap.expire(), KeymapResult::NoMatch(vec![Key::Char('a')]));
+        assert!(!keymap.has_queued());
+        assert!(!keymap.is_pending());
+
+        // The second j starts the binding again.
+        assert_eq!(keymap.feed(Key::Char('j')), KeymapResult::Pending);
+        assert_eq!(
+            keymap.feed(Key::Char('j')),
+            KeymapResult::NoMatch(vec![Key::Char('j')])
+        );
+        assert_eq!(keymap.feed(Key::Char('k')), KeymapResult::Matched("escape"));
+    }
+
+    /// Feed `key`, and collect the results it resolves like the module example.
+    fn feed(keymap: &mut Keymap<&'static str>, key: Key) -> Vec<KeymapResult<&'static str>> {
+        let mut results = vec![keymap.feed(key)];
+        while keymap.has_queued() {
+            results.push(keymap.expire());
+        }
+        results
+    }
+
+    #[test]
+    fn test_several_results() {
+        let mut keymap = keymap();
+        assert_eq!(feed(&mut keymap, Key::Ctrl('x')), [KeymapResult::Pending]);
+        assert_eq!(
+            feed(&mut keymap, Key::Char('a')),
+            [
+                KeymapResult::NoMatch(vec![Key::Ctrl('x')]),
+                KeymapResult::NoMatch(vec![Key::Char('a')]),
+            ]
+        );
+
+        // Keys starting another binding stay pending.
+        assert_eq!(feed(&mut keymap, Key::Ctrl('x')), [KeymapResult::Pending]);
+        assert_eq!(
+            feed(&mut keymap, Key::Char('j')),
+            [
+                KeymapResult::NoMatch(vec![Key::Ctrl('x')]),
+                KeymapResult::Pending,
+            ]
+        );
+        assert_eq!(
+            feed(&mut keymap, Key::Char('k')),
+            [KeymapResult::Matched("escape")]
+        );
+        assert!(!keymap.is_pending());
+    }
+
+    #[test]
+    fn test_ambiguous_sequences() {
+        let mut keymap = keymap();
+        assert_eq!(keymap.feed(Key::Char('g')), KeymapResult::Pending);
+        assert_eq!(keymap.feed(Key::Char('g')), KeymapResult::Matched("top"));
+
+        assert_eq!(keymap.feed(Key::Char('g')), KeymapResult::Pending);
+        assert_eq!(keymap.expire(), KeymapResult::Matched("go"));
+        assert_eq!(keymap.expire(), KeymapResult::Pending);
+
+        assert_eq!(keymap.feed(Key::Char('g')), KeymapResult::Pending);
+        assert_eq!(keymap.feed(Key::Ctrl('x')), KeymapResult::Matched("go"));
+        assert_eq!(keymap.expire(), KeymapResult::Pending);
+        assert_eq!(keymap.feed(Key::Ctrl('c')), KeymapResult::Matched("quit"));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut keymap = Keymap::with_timeout(Duration::from_millis(0));
+        keymap.bind(&[Key::Char('g')], 1);
+        keymap.bind(&[Key::Char('g'), Key::Char('g')], 2);
+        assert_eq!(keymap.bind(&[Key::Char('g')], 3), Some(1));
+        assert_eq!(keymap.get(&[Key::Char('g')]), Some(&3));
+
+        assert_eq!(keymap.feed(Key::Char('g')), KeymapResult::Pending);
+        // Waiting for the rest of the sequence timed out, but nothing is queued.
+        assert_eq!(keymap.timeout(), Some(Duration::from_secs(0)));
+        assert!(!keymap.has_queued());
+        keymap.clear_pending();
+        assert_eq!(keymap.timeout(), None);
+    }
+}
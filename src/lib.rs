@@ -12,6 +12,8 @@
 #![warn(missing_docs)]
 
 extern crate numtoa;
+#[cfg(feature = "futures")]
+extern crate futures_core;
 #[cfg(feature = "serde")]
 extern crate serde;
 
@@ -30,18 +32,28 @@ pub use sys::tty::{get_tty, is_tty};
 
 mod r#async;
 pub use r#async::{async_stdin, AsyncReader};
+#[cfg(all(unix, not(target_os = "redox")))]
+pub use r#async::{poll_stdin, PollingReader};
 
 #[macro_use]
 mod macros;
+mod grapheme;
 pub mod clear;
 pub mod color;
 pub mod cursor;
+pub mod editor;
 pub mod event;
 pub mod input;
+pub mod keymap;
+pub mod passwd;
 pub mod raw;
+pub mod record;
 pub mod screen;
 pub mod scroll;
+#[cfg(all(feature = "futures", unix, not(target_os = "redox")))]
+pub mod stream;
 pub mod style;
+pub mod terminfo;
 
 #[cfg(test)]
 mod test {
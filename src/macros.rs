@@ -29,3 +29,40 @@ macro_rules! derive_csi_sequence {
         }
     };
 }
+
+/// Implement `Deref`, `DerefMut`, `Write` and `AsRawFd` for a terminal wrapper, forwarding to the
+/// writer stored in its `term` field.
+macro_rules! impl_terminal_wrapper {
+    ($name:ident) => {
+        impl<W: Write> ::std::ops::Deref for $name<W> {
+            type Target = W;
+
+            fn deref(&self) -> &W {
+                &self.term
+            }
+        }
+
+        impl<W: Write> ::std::ops::DerefMut for $name<W> {
+            fn deref_mut(&mut self) -> &mut W {
+                &mut self.term
+            }
+        }
+
+        impl<W: Write> Write for $name<W> {
+            fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+                self.term.write(buf)
+            }
+
+            fn flush(&mut self) -> ::std::io::Result<()> {
+                self.term.flush()
+            }
+        }
+
+        #[cfg(unix)]
+        impl<W: Write + ::std::os::unix::io::AsRawFd> ::std::os::unix::io::AsRawFd for $name<W> {
+            fn as_raw_fd(&self) -> ::std::os::unix::io::RawFd {
+                self.term.as_raw_fd()
+            }
+        }
+    };
+}
@@ -0,0 +1,413 @@
+//! Reading passwords.
+//!
+//! `PasswordReader` reads a secret from the terminal without echoing it, optionally showing a mask
+//! character for every character typed. The secret is kept in a `Password`, which overwrites its
+//! memory with zeroes when dropped.
+//!
+//! | Key                                  | Action                        |
+//! |--------------------------------------|-------------------------------|
+//! | Backspace, Ctrl-H                    | Delete the last character     |
+//! | Ctrl-U                               | Delete everything typed       |
+//! | Enter                                | Accept the password           |
+//! | Ctrl-C, Ctrl-D                       | Abort                         |
+//! | Arrows, function keys, Alt chords    | Ignored                       |
+
+use std::io::{self, Read, Write};
+use std::sync::atomic::{self, Ordering};
+use std::{fmt, ptr, str};
+
+use clear;
+use cursor;
+use get_tty;
+use grapheme::{self, next_boundary, prev_boundary};
+use raw::IntoRawMode;
+
+/// A password, zeroed when dropped.
+///
+/// The bytes are never moved to a new allocation without zeroing the old one, so no copies are
+/// left behind in freed memory. Its `Debug` output does not show the password.
+pub struct Password {
+    bytes: Vec<u8>,
+}
+
+impl Password {
+    fn new() -> Password {
+        Password {
+            bytes: Vec::with_capacity(64),
+        }
+    }
+
+    /// The password.
+    ///
+    /// Returns an error if the input was not valid UTF-8.
+    pub fn as_str(&self) -> io::Result<&str> {
+        str::from_utf8(&self.bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The password as entered.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The length of the password in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the password is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The longest valid UTF-8 prefix, leaving out an incomplete character being typed.
+    fn valid(&self) -> &str {
+        match str::from_utf8(&self.bytes) {
+            Ok(s) => s,
+            Err(e) => str::from_utf8(&self.bytes[..e.valid_up_to()]).unwrap(),
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.bytes.len() == self.bytes.capacity() {
+            let mut bytes = Vec::with_capacity(self.bytes.capacity() * 2);
+            bytes.extend_from_slice(&self.bytes);
+            zero(&mut self.bytes);
+            self.bytes = bytes;
+        }
+        self.bytes.push(byte);
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.bytes.len() {
+            zero(&mut self.bytes[len..]);
+            self.bytes.truncate(len);
+        }
+    }
+}
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        zero(&mut self.bytes);
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Password(..)")
+    }
+}
+
+/// Overwrite `bytes` with zeroes, in a way the compiler does not optimize out.
+fn zero(bytes: &mut [u8]) {
+    for byte in bytes {
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// A password prompt.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use termion::passwd::PasswordReader;
+///
+/// match PasswordReader::new().mask('*').read_tty("password: ")? {
+///     Some(password) => println!("{} bytes", password.len()),
+///     None => println!("aborted"),
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PasswordReader {
+    mask: Option<char>,
+}
+
+impl PasswordReader {
+    /// Create a reader not echoing anything.
+    pub fn new() -> PasswordReader {
+        PasswordReader::default()
+    }
+
+    /// Echo `mask` for every character typed.
+    ///
+    /// Characters are counted as they are seen, so a letter followed by a combining accent, or an
+    /// emoji made of several code points, shows a single mask.
+    pub fn mask(mut self, mask: char) -> PasswordReader {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Read a password from the controlling terminal, see `get_tty`.
+    ///
+    /// The prompt is written to the terminal, which is put in raw mode while reading. This works
+    /// even when stdin and stdout are redirected.
+    pub fn read_tty(&self, prompt: &str) -> io::Result<Option<Password>> {
+        let mut tty = get_tty()?;
+        let mut out = tty.try_clone()?.into_raw_mode()?;
+        self.read(prompt, &mut tty, &mut out)
+    }
+
+    /// Read a password from `input`, writing the prompt and masks to `out`.
+    ///
+    /// The terminal must be in raw mode while reading, see `raw::IntoRawMode`. Returns `None` if
+    /// the prompt was aborted.
+    pub fn read<R: Read, W: Write>(
+        &self,
+        prompt: &str,
+        input: &mut R,
+        out: &mut W,
+    ) -> io::Result<Option<Password>> {
+        write!(out, "{}", prompt)?;
+        out.flush()?;
+
+        let mut password = Password::new();
+        let mut masked = 0;
+        let mut buf = [0; 32];
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let accepted = self.take(&buf[..n], &mut password, &mut masked, out);
+            zero(&mut buf);
+            match accepted? {
+                Some(true) => break,
+                Some(false) => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                None => {}
+            }
+        }
+
+        write!(out, "\r\n")?;
+        out.flush()?;
+        Ok(Some(password))
+    }
+
+    /// Take the bytes read into the password, updating the masks.
+    ///
+    /// Returns whether the password was accepted or aborted, if either key was read.
+    fn take<W: Write>(
+        &self,
+        bytes: &[u8],
+        password: &mut Password,
+        masked: &mut usize,
+        out: &mut W,
+    ) -> io::Result<Option<bool>> {
+        // Keys send their escape sequences in a single write, so a sequence never continues past
+        // the bytes read, and a lone ESC ends them.
+        let mut escape = Escape::None;
+        for &c in bytes {
+            if escape.skips(c) {
+                continue;
+            }
+            match c {
+                0 | 3 | 4 => return Ok(Some(false)),
+                b'\n' | b'\r' => return Ok(Some(true)),
+                0x7f | 0x08 => {
+                    let valid = password.valid();
+                    let len = if valid.len() < password.len() {
+                        valid.len()
+                    } else {
+                        prev_boundary(valid, valid.len())
+                    };
+                    password.truncate(len);
+                }
+                0x15 => password.truncate(0),
+                0x1B => escape = Escape::Started,
+                c => password.push(c),
+            }
+
+            if let Some(mask) = self.mask {
+                *masked = update_masks(out, mask, *masked, clusters(password.valid()))?;
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Where the bytes read are in an escape sequence, which is not part of the password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    /// Not in a sequence.
+    None,
+    /// After ESC.
+    Started,
+    /// In a CSI or SS3 sequence, before its final byte.
+    Sequence,
+    /// In the rest of a character typed with Alt.
+    Alt,
+}
+
+impl Escape {
+    /// Whether `byte` is part of the sequence, moving on past it.
+    fn skips(&mut self, byte: u8) -> bool {
+        let (next, skips) = match (*self, byte) {
+            (Escape::None, _) => (Escape::None, false),
+            (Escape::Started, b'[') | (Escape::Started, b'O') => (Escape::Sequence, true),
+            // Control characters keep their meaning after ESC.
+            (Escape::Started, 0..=0x1F) | (Escape::Started, 0x7F) => (Escape::None, false),
+            (Escape::Started, 0xC0..=0xFF) => (Escape::Alt, true),
+            (Escape::Started, _) => (Escape::None, true),
+            // Parameter and intermediate bytes, then the final byte.
+            (Escape::Sequence, 0x20..=0x3F) => (Escape::Sequence, true),
+            (Escape::Sequence, 0x40..=0x7E) => (Escape::None, true),
+            (Escape::Alt, 0x80..=0xBF) => (Escape::Alt, true),
+            // A malformed sequence ends before the first byte that cannot be part of it.
+            (Escape::Sequence, _) | (Escape::Alt, _) => (Escape::None, false),
+        };
+        *self = next;
+        skips
+    }
+}
+
+/// The number of grapheme clusters in `s`.
+fn clusters(s: &str) -> usize {
+    let mut count = 0;
+    let mut pos = 0;
+    while pos < s.len() {
+        pos = next_boundary(s, pos);
+        count += 1;
+    }
+    count
+}
+
+/// Show `count` masks where `masked` are shown, returning the new count.
+fn update_masks<W: Write>(
+    out: &mut W,
+    mask: char,
+    masked: usize,
+    count: usize,
+) -> io::Result<usize> {
+    if count > masked {
+        for _ in masked..count {
+            write!(out, "{}", mask)?;
+        }
+    } else if count < masked {
+        let width = grapheme::width(mask.encode_utf8(&mut [0; 4])).max(1);
+        write!(
+            out,
+            "{}{}",
+            cursor::Left(((masked - count) * width) as u16),
+            clear::UntilNewline
+        )?;
+    } else {
+        return Ok(masked);
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(reader: &PasswordReader, input: &[u8]) -> (Option<String>, String) {
+        let mut out = Vec::new();
+        let password = reader.read("pw: ", &mut &input[..], &mut out).unwrap();
+        let password = password.map(|p| p.as_str().unwrap().to_string());
+        (password, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_no_echo() {
+        let reader = PasswordReader::new();
+        assert_eq!(
+            read(&reader, b"secret\r"),
+            (Some("secret".into()), "pw: \r\n".into())
+        );
+        assert_eq!(read(&reader, b"ab\x7Fc\n").0, Some("ac".into()));
+        assert_eq!(read(&reader, b"abc\x15de\r").0, Some("de".into()));
+        assert_eq!(read(&reader, b"abc\x03"), (None, "pw: \r\n".into()));
+        assert_eq!(read(&reader, b"abc\x04").0, None);
+        assert_eq!(read(&reader, b"abc").0, Some("abc".into()));
+    }
+
+    #[test]
+    fn test_escape_sequences() {
+        let reader = PasswordReader::new().mask('*');
+        assert_eq!(
+            read(&reader, b"ab\x1B[Dc\x1BOH\x1B[1;5C\x1B[15~d\r"),
+            (Some("abcd".into()), "pw: ****\r\n".into())
+        );
+        // Keys typed with Alt are dropped along with their ESC.
+        assert_eq!(read(&reader, b"a\x1Bb\x1B\xC3\xA9c\r").0, Some("ac".into()));
+        assert_eq!(read(&reader, b"a\x1B\x1B[Ab\r").0, Some("ab".into()));
+        // A malformed sequence ends at the first byte that cannot be part of it.
+        assert_eq!(read(&reader, b"ab\x1B[1\x7Fc\r").0, Some("ac".into()));
+        assert_eq!(read(&reader, b"ab\x1B[1\x03").0, None);
+
+        // A lone ESC ends the bytes read, and the next key is kept.
+        let mut input = Chunks(vec![b"a", b"\x1B", b"b", b"\x1BO", b"c\r"]);
+        let password = reader.read("", &mut input, &mut Vec::new()).unwrap();
+        assert_eq!(password.unwrap().as_str().unwrap(), "abc");
+    }
+
+    /// A source returning one chunk per read.
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_mask() {
+        let reader = PasswordReader::new().mask('*');
+        assert_eq!(
+            read(&reader, b"ab\x7Fc\r"),
+            (Some("ac".into()), "pw: **\x1B[1D\x1B[K*\r\n".into())
+        );
+        assert_eq!(
+            read(&reader, b"abc\x15d\r"),
+            (Some("d".into()), "pw: ***\x1B[3D\x1B[K*\r\n".into())
+        );
+
+        // One mask per grapheme cluster, whatever its length in bytes.
+        let input = "e\u{301}\u{65E5}\u{1F469}\u{1F3FD}\u{200D}\u{1F4BB}\x7F\r";
+        assert_eq!(
+            read(&reader, input.as_bytes()),
+            (
+                Some("e\u{301}\u{65E5}".into()),
+                "pw: ***\x1B[1D\x1B[K\r\n".into()
+            )
+        );
+
+        // Wide masks are erased by their width.
+        let reader = PasswordReader::new().mask('\u{25CF}');
+        assert_eq!(read(&reader, b"a\x08\r").1, "pw: \u{25CF}\x1B[1D\x1B[K\r\n");
+        let reader = PasswordReader::new().mask('\u{1F511}');
+        assert_eq!(
+            read(&reader, b"a\x08\r").1,
+            "pw: \u{1F511}\x1B[2D\x1B[K\r\n"
+        );
+    }
+
+    #[test]
+    fn test_password() {
+        let mut password = Password::new();
+        for &b in b"0123456789".iter().cycle().take(100) {
+            password.push(b);
+        }
+        assert_eq!(password.len(), 100);
+        assert_eq!(&password.as_bytes()[..12], b"012345678901");
+
+        password.truncate(3);
+        assert_eq!(password.as_str().unwrap(), "012");
+        assert_eq!(format!("{:?}", password), "Password(..)");
+
+        password.push(0xE6);
+        assert_eq!(password.valid(), "012");
+        assert!(password.as_str().is_err());
+    }
+}
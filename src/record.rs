@@ -0,0 +1,280 @@
+//! Recording and replaying input.
+//!
+//! A `Recorder` wraps an input source, writing every chunk of bytes read from it to a recording,
+//! along with the time it arrived. A `Replay` reads the recording back as a source, so that the
+//! same events are parsed from it again, for example to reproduce a bug in a test.
+//!
+//! Recordings are text. After a header line, every chunk takes a line holding the microseconds
+//! since the previous chunk (or since recording started) and the bytes in hexadecimal:
+//!
+//! ```text
+//! termion-recording 1
+//! 1520000 61
+//! 800000 1b5b41
+//! ```
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::fs::File;
+//! use std::io::stdin;
+//! use termion::input::TermRead;
+//! use termion::record::{Pacing, Recorder, Replay};
+//!
+//! // Record a session.
+//! let recorder = Recorder::new(stdin(), File::create("session.rec")?)?;
+//! for event in recorder.events() {
+//!     println!("{:?}", event?);
+//! }
+//!
+//! // And replay it as fast as possible.
+//! let replay = Replay::new(File::open("session.rec")?)?.pacing(Pacing::Instant);
+//! for event in replay.events() {
+//!     println!("{:?}", event?);
+//! }
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The first line of a recording.
+const HEADER: &str = "termion-recording 1";
+
+/// An input source writing what is read from it to a recording.
+///
+/// Only bytes actually read are recorded, so a parser reading from the recorder records exactly
+/// the bytes it consumed. Errors and reads of no bytes are not recorded.
+#[derive(Debug)]
+pub struct Recorder<R, W> {
+    source: R,
+    out: W,
+    last: Instant,
+}
+
+impl<R: Read, W: Write> Recorder<R, W> {
+    /// Record the input read from `source` to `out`.
+    pub fn new(source: R, mut out: W) -> io::Result<Recorder<R, W>> {
+        writeln!(out, "{}", HEADER)?;
+        Ok(Recorder {
+            source,
+            out,
+            last: Instant::now(),
+        })
+    }
+
+    /// Stop recording, returning the source and the recording.
+    pub fn into_inner(self) -> (R, W) {
+        (self.source, self.out)
+    }
+}
+
+impl<R: Read, W: Write> Read for Recorder<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.source.read(buf)?;
+        if n > 0 {
+            let now = Instant::now();
+            let delay = now.duration_since(self.last);
+            self.last = now;
+
+            write!(self.out, "{}", delay.as_micros())?;
+            let mut sep = " ";
+            for byte in &buf[..n] {
+                write!(self.out, "{}{:02x}", sep, byte)?;
+                sep = "";
+            }
+            writeln!(self.out)?;
+            self.out.flush()?;
+        }
+        Ok(n)
+    }
+}
+
+/// How a `Replay` paces its chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Wait between chunks as long as they were apart when recorded.
+    RealTime,
+    /// Return chunks as soon as they are read.
+    Instant,
+}
+
+/// An input source replaying a recording.
+///
+/// Reads return at most one recorded chunk at a time, and end once all chunks were read.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    chunks: Vec<(Duration, Vec<u8>)>,
+    /// The index of the next chunk.
+    next: usize,
+    /// How much of the next chunk was read.
+    offset: usize,
+    pacing: Pacing,
+    /// When the previous chunk was returned.
+    last: Option<Instant>,
+}
+
+impl Replay {
+    /// Load a recording written by a `Recorder`, replaying it in real time.
+    pub fn new<R: Read>(recording: R) -> io::Result<Replay> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut lines = BufReader::new(recording).lines();
+        let header = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+        if header.trim_end() != HEADER {
+            return Err(invalid("not a recording"));
+        }
+
+        let mut chunks = Vec::new();
+        for line in lines {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split(' ');
+            let delay = fields
+                .next()
+                .and_then(|delay| delay.parse().ok())
+                .map(Duration::from_micros)
+                .ok_or_else(|| invalid("invalid delay in recording"))?;
+            let bytes = fields
+                .next()
+                .and_then(parse_hex)
+                .ok_or_else(|| invalid("invalid bytes in recording"))?;
+            if fields.next().is_some() {
+                return Err(invalid("invalid line in recording"));
+            }
+            chunks.push((delay, bytes));
+        }
+
+        Ok(Replay {
+            chunks,
+            next: 0,
+            offset: 0,
+            pacing: Pacing::RealTime,
+            last: None,
+        })
+    }
+
+    /// Set how the chunks are paced.
+    pub fn pacing(mut self, pacing: Pacing) -> Replay {
+        self.pacing = pacing;
+        self
+    }
+
+    /// The bytes left to replay.
+    pub fn remaining(&self) -> usize {
+        let chunks = self.chunks.iter().skip(self.next);
+        chunks.map(|(_, bytes)| bytes.len()).sum::<usize>() - self.offset
+    }
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let delay = match self.chunks.get(self.next) {
+            Some(&(delay, _)) => delay,
+            None => return Ok(0),
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.offset == 0 && self.pacing == Pacing::RealTime {
+            if let Some(last) = self.last {
+                let elapsed = last.elapsed();
+                if delay > elapsed {
+                    thread::sleep(delay - elapsed);
+                }
+            }
+        }
+
+        let bytes = &self.chunks[self.next].1[self.offset..];
+        let n = buf.len().min(bytes.len());
+        buf[..n].copy_from_slice(&bytes[..n]);
+        if n == bytes.len() {
+            self.next += 1;
+            self.offset = 0;
+            self.last = Some(Instant::now());
+        } else {
+            self.offset += n;
+        }
+        Ok(n)
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if s.is_empty() {
+        return None;
+    }
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match *pair {
+            [high, low] => Some(hex_digit(high)? << 4 | hex_digit(low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|digit| digit as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::{Event, Key, KeyCode, Modifiers};
+    use input::TermRead;
+
+    #[test]
+    fn test_record_and_replay() {
+        let input: &[u8] = b"a\x1B[A\x1B[1;5Cb";
+        let mut recorder = Recorder::new(input, Vec::new()).unwrap();
+        let events: Vec<Event> = (&mut recorder).events().map(Result::unwrap).collect();
+        let (_, recording) = recorder.into_inner();
+
+        let text = String::from_utf8(recording.clone()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(HEADER));
+        assert!(lines.all(|line| line.split(' ').count() == 2));
+
+        let replay = Replay::new(&recording[..]).unwrap();
+        assert_eq!(replay.remaining(), input.len());
+        let replay = replay.pacing(Pacing::Instant);
+        let replayed: Vec<Event> = replay.events().map(Result::unwrap).collect();
+        assert_eq!(replayed, events);
+        assert_eq!(
+            replayed,
+            [
+                Event::Key(Key::Char('a')),
+                Event::Key(Key::Up),
+                Event::Key(Key::Modified(KeyCode::Right, Modifiers::CTRL)),
+                Event::Key(Key::Char('b')),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let recording = format!("{}\n0 6162\n\n30000 1b5b42\n", HEADER);
+        let mut replay = Replay::new(recording.as_bytes()).unwrap();
+        let mut buf = [0; 2];
+        assert_eq!(replay.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf, b"ab");
+
+        let start = Instant::now();
+        assert_eq!(replay.read(&mut buf[..1]).unwrap(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(replay.remaining(), 2);
+        assert_eq!(replay.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf, b"[B");
+        assert_eq!(replay.read(&mut buf).unwrap(), 0);
+
+        assert!(Replay::new(&b"0 61\n"[..]).is_err());
+        for line in ["x 61", "0 6", "0 zz", "0", "0 61 62"].iter() {
+            let recording = format!("{}\n{}\n", HEADER, line);
+            assert!(Replay::new(recording.as_bytes()).is_err(), "{}", line);
+        }
+    }
+}
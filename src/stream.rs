@@ -0,0 +1,314 @@
+//! A `Stream` of input events, for use with async runtimes.
+//!
+//! This module requires the `futures` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # extern crate futures_core;
+//! # extern crate termion;
+//! use futures_core::Stream;
+//! use termion::stream::EventStream;
+//!
+//! fn events() -> std::io::Result<impl Stream<Item = std::io::Result<termion::event::Event>>> {
+//!     // Poll it with `StreamExt::next` in an async function, or in `select!` along with other
+//!     // futures.
+//!     EventStream::tty()
+//! }
+//! # fn main() {}
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use event::{Event, ParseOptions};
+use get_tty;
+use input::InputParser;
+use raw::CONTROL_SEQUENCE_TIMEOUT;
+use sys::poll::{poll_read, poll_read_any};
+
+/// A stream of input events.
+///
+/// Waiting for input is left to a helper thread, which only polls the source for readiness and
+/// wakes the task reading the stream. All input is read and parsed by the stream itself, so no
+/// input is lost once it is dropped, and the thread is stopped along with it.
+///
+/// Like `input::EventsWithTimeout`, partial escape sequences are completed once the rest of their
+/// bytes arrive, and a lone ESC is reported as `Key::Esc` once no more input arrived for a while.
+/// The stream ends at the end of the input.
+pub struct EventStream<R: AsFd> {
+    source: R,
+    parser: InputParser,
+    timeout: Duration,
+    /// When the parser was last given input, while it holds a partial sequence.
+    pending_since: Option<Instant>,
+    shared: Arc<Shared>,
+    /// Interrupts the helper thread when the task to wake changes.
+    notify: UnixStream,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The state shared with the helper thread.
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// The task waiting for input, if any.
+    waker: Option<Waker>,
+    /// When to wake the task even without input.
+    deadline: Option<Instant>,
+    /// Whether the stream was dropped.
+    closed: bool,
+}
+
+impl EventStream<File> {
+    /// A stream of the events of the TTY, see `get_tty`.
+    pub fn tty() -> io::Result<EventStream<File>> {
+        EventStream::new(get_tty()?)
+    }
+}
+
+impl<R: Read + AsFd> EventStream<R> {
+    /// A stream of the events read from `source`, waiting `raw::CONTROL_SEQUENCE_TIMEOUT`
+    /// milliseconds for the rest of an escape sequence.
+    pub fn new(source: R) -> io::Result<EventStream<R>> {
+        EventStream::with_timeout(source, Duration::from_millis(CONTROL_SEQUENCE_TIMEOUT))
+    }
+
+    /// A stream of the events read from `source`, waiting `timeout` for the rest of an escape
+    /// sequence.
+    pub fn with_timeout(source: R, timeout: Duration) -> io::Result<EventStream<R>> {
+        let (notify, notified) = UnixStream::pair()?;
+        // Notifications are only needed while the thread has not seen the last one yet, so
+        // neither end ever waits.
+        notify.set_nonblocking(true)?;
+        notified.set_nonblocking(true)?;
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        });
+
+        let fd = source.as_fd().as_raw_fd();
+        let thread_shared = shared.clone();
+        let thread = thread::Builder::new()
+            .name("termion-events".into())
+            .spawn(move || wait_for_input(fd, notified, &thread_shared))?;
+
+        Ok(EventStream {
+            source,
+            parser: InputParser::new(),
+            timeout,
+            pending_since: None,
+            shared,
+            notify,
+            thread: Some(thread),
+        })
+    }
+
+    /// Set how input is parsed, see `ParseOptions`.
+    pub fn options(mut self, options: ParseOptions) -> EventStream<R> {
+        self.parser = mem::take(&mut self.parser).options(options);
+        self
+    }
+
+    /// Read the input that is available, returning `false` at the end of the input.
+    fn read_available(&mut self) -> io::Result<bool> {
+        let mut buf = [0; 1024];
+        while poll_read(self.source.as_fd(), Some(Duration::from_secs(0)))? {
+            match self.source.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    self.parser.push(&buf[..n]);
+                    self.pending_since = Some(Instant::now());
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Have the helper thread wake the task on input, or once `deadline` passes.
+    fn register(&mut self, waker: &Waker, deadline: Option<Instant>) -> io::Result<()> {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.waker = Some(waker.clone());
+            state.deadline = deadline;
+        }
+        self.shared.changed.notify_one();
+        // Interrupt the thread if it is polling already, so that it sees the new deadline.
+        match self.notify.write(&[0]) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+impl<R: Read + AsFd + Unpin> Stream for EventStream<R> {
+    type Item = io::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<io::Result<Event>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some((event, _raw)) = this.parser.next() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            match this.read_available() {
+                Ok(true) => {}
+                Ok(false) => {
+                    // Report what is left of a partial sequence before ending.
+                    this.parser.flush_pending();
+                    return Poll::Ready(this.parser.next().map(|(event, _raw)| Ok(event)));
+                }
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+            if let Some((event, _raw)) = this.parser.next() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            let deadline = match this.pending_since {
+                Some(since) if this.parser.has_pending() => {
+                    let deadline = since + this.timeout;
+                    if Instant::now() >= deadline {
+                        this.parser.flush_pending();
+                        continue;
+                    }
+                    Some(deadline)
+                }
+                _ => None,
+            };
+
+            if let Err(e) = this.register(cx.waker(), deadline) {
+                return Poll::Ready(Some(Err(e)));
+            }
+            // Input may have arrived before the thread was told to look for it, but it then
+            // finds the source readable right away.
+            return Poll::Pending;
+        }
+    }
+}
+
+impl<R: AsFd> Drop for EventStream<R> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.changed.notify_one();
+        let _ = self.notify.write(&[0]);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Wake the waiting task whenever `fd` is readable or its deadline passed, until the stream is
+/// closed.
+fn wait_for_input(fd: RawFd, mut notified: UnixStream, shared: &Shared) {
+    // The stream keeps the source open until this thread was joined.
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    loop {
+        let deadline = {
+            let mut state = shared.state.lock().unwrap();
+            while state.waker.is_none() && !state.closed {
+                state = shared.changed.wait(state).unwrap();
+            }
+            if state.closed {
+                return;
+            }
+            state.deadline
+        };
+
+        let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let ready = match poll_read_any(&[fd, notified.as_fd()], timeout) {
+            Ok(ready) => ready,
+            // Let the stream run into the error itself.
+            Err(_) => vec![true, false],
+        };
+        if ready[1] {
+            // Drain the notifications, and look at the state again.
+            while let Ok(n) = notified.read(&mut [0; 64]) {
+                if n == 0 {
+                    return;
+                }
+            }
+            if !ready[0] {
+                continue;
+            }
+        }
+
+        let mut state = shared.state.lock().unwrap();
+        let timed_out = state.deadline.is_some_and(|d| Instant::now() >= d);
+        if ready[0] || timed_out {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::Key;
+    use std::sync::mpsc;
+    use std::task::Wake;
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match Pin::new(&mut *stream).poll_next(&mut cx) {
+                Poll::Ready(item) => return item,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let mut stream = EventStream::with_timeout(rx, Duration::from_millis(50)).unwrap();
+
+        let (step, wait) = mpsc::channel();
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.write_all(b"a\x1B[").unwrap();
+            thread::sleep(Duration::from_millis(20));
+            tx.write_all(b"A\x1B").unwrap();
+            wait.recv().unwrap();
+        });
+
+        let next_event = |stream: &mut EventStream<UnixStream>| next(stream).unwrap().unwrap();
+        assert_eq!(next_event(&mut stream), Event::Key(Key::Char('a')));
+        // A sequence split across writes is still a single key.
+        assert_eq!(next_event(&mut stream), Event::Key(Key::Up));
+        // A lone ESC is the Esc key once the timeout elapses.
+        let start = Instant::now();
+        assert_eq!(next_event(&mut stream), Event::Key(Key::Esc));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+
+        step.send(()).unwrap();
+        writer.join().unwrap();
+        assert!(next(&mut stream).is_none());
+    }
+}
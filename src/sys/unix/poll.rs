@@ -0,0 +1,51 @@
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::time::{Duration, Instant};
+
+use super::cvt;
+use super::libc::{self, c_int};
+
+/// Wait until `fd` is readable, or until `timeout` elapses.
+///
+/// A timeout of `None` waits indefinitely. Returns whether the file descriptor is readable.
+pub fn poll_read(fd: BorrowedFd, timeout: Option<Duration>) -> io::Result<bool> {
+    Ok(poll_read_any(&[fd], timeout)?[0])
+}
+
+/// Wait until any of `fds` is readable, or until `timeout` elapses.
+///
+/// A timeout of `None` waits indefinitely. Returns whether each file descriptor is readable.
+pub fn poll_read_any(fds: &[BorrowedFd], timeout: Option<Duration>) -> io::Result<Vec<bool>> {
+    // A deadline too far off to represent is as good as none.
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
+    let mut pollfds: Vec<libc::pollfd> = fds
+        .iter()
+        .map(|fd| libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+
+    loop {
+        // Retries after a signal only wait for what is left of the timeout.
+        let timeout = match deadline {
+            // Round up, so that a non-zero timeout does not turn into a busy loop.
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let millis = timeout.as_nanos().div_ceil(1_000_000);
+                millis.min(c_int::MAX as u128) as c_int
+            }
+            None => -1,
+        };
+        let ready =
+            unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout) };
+        match cvt(ready) {
+            // Hang ups and errors are reported as readable, so that reading reports them.
+            Ok(_) => return Ok(pollfds.iter().map(|pollfd| pollfd.revents != 0).collect()),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
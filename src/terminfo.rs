@@ -0,0 +1,461 @@
+//! Key sequences from the terminfo database.
+//!
+//! The parser recognizes the sequences sent by xterm, rxvt, the Linux console and terminals
+//! compatible with them. Terminals encoding some keys differently can be supported by loading
+//! their compiled terminfo entry, and passing it to the parser:
+//!
+//! ```rust,no_run
+//! use std::io::stdin;
+//! use termion::event::ParseOptions;
+//! use termion::input::TermRead;
+//! use termion::terminfo::Terminfo;
+//!
+//! // Without an entry for $TERM, only the built-in sequences are recognized.
+//! let options = ParseOptions::default().terminfo(Terminfo::from_env().ok());
+//! for event in stdin().events().options(options) {
+//!     println!("{:?}", event?);
+//! }
+//! # std::io::Result::Ok(())
+//! ```
+//!
+//! The key strings of the entry take precedence over the built-in sequences. Like the entry, they
+//! assume that the terminal is in application keypad mode, though most terminals send the same
+//! sequences either way.
+
+use std::env;
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::PathBuf;
+
+use event::{Key, KeyCode, Modifiers};
+
+/// The magic number of the legacy format, with 16 bit numbers.
+const MAGIC: u16 = 0o432;
+/// The magic number of the extended number format, with 32 bit numbers.
+const MAGIC_32BIT: u16 = 0o1036;
+
+/// The directories searched for entries after `$TERMINFO`, `~/.terminfo` and `$TERMINFO_DIRS`.
+const DIRS: &[&str] = &[
+    "/etc/terminfo",
+    "/lib/terminfo",
+    "/usr/share/terminfo",
+    "/usr/lib/terminfo",
+    "/usr/local/share/terminfo",
+];
+
+/// The string capabilities holding key sequences, by index, besides the function keys.
+const KEYS: &[(usize, Key)] = &[
+    // kbs
+    (55, Key::Backspace),
+    // kdch1
+    (59, Key::Delete),
+    // kcud1
+    (61, Key::Down),
+    // khome
+    (76, Key::Home),
+    // kich1
+    (77, Key::Insert),
+    // kcub1
+    (79, Key::Left),
+    // knp
+    (81, Key::PageDown),
+    // kpp
+    (82, Key::PageUp),
+    // kcuf1
+    (83, Key::Right),
+    // kcuu1
+    (87, Key::Up),
+    // kcbt
+    (148, Key::BackTab),
+    // kend
+    (164, Key::End),
+    // kent
+    (165, Key::Keypad('\n')),
+    // kDC
+    (191, Key::Modified(KeyCode::Delete, Modifiers::SHIFT)),
+    // kEND
+    (194, Key::Modified(KeyCode::End, Modifiers::SHIFT)),
+    // kHOM
+    (199, Key::Modified(KeyCode::Home, Modifiers::SHIFT)),
+    // kIC
+    (200, Key::Modified(KeyCode::Insert, Modifiers::SHIFT)),
+    // kLFT
+    (201, Key::Modified(KeyCode::Left, Modifiers::SHIFT)),
+    // kRIT
+    (210, Key::Modified(KeyCode::Right, Modifiers::SHIFT)),
+];
+
+/// The indices of kf1 to kf10, which are not in order.
+const FUNCTION_KEYS: &[usize] = &[66, 68, 69, 70, 71, 72, 73, 74, 75, 67];
+/// The index of kf11, followed by kf12 to kf63.
+const FUNCTION_KEYS_FROM_11: usize = 216;
+
+/// The key sequences of a terminal, from its terminfo entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminfo {
+    names: Vec<String>,
+    keys: Vec<(Vec<u8>, Key)>,
+}
+
+impl Terminfo {
+    /// Load the entry for the terminal named by `$TERM`.
+    pub fn from_env() -> io::Result<Terminfo> {
+        match env::var("TERM") {
+            Ok(term) => Terminfo::load(&term),
+            Err(_) => Err(Error::new(ErrorKind::NotFound, "TERM is not set")),
+        }
+    }
+
+    /// Load the entry for the terminal `term`.
+    ///
+    /// The entry is looked up in `$TERMINFO`, `~/.terminfo`, the directories in `$TERMINFO_DIRS`,
+    /// and then the usual system directories, in that order.
+    pub fn load(term: &str) -> io::Result<Terminfo> {
+        let first = match term.chars().next() {
+            Some(first) if !term.contains('/') && term != "." && term != ".." => first,
+            _ => return Err(Error::new(ErrorKind::InvalidInput, "invalid terminal name")),
+        };
+
+        let mut dirs = Vec::new();
+        if let Some(dir) = env::var_os("TERMINFO") {
+            dirs.push(PathBuf::from(dir));
+        }
+        if let Some(home) = env::var_os("HOME") {
+            dirs.push(PathBuf::from(home).join(".terminfo"));
+        }
+        if let Ok(list) = env::var("TERMINFO_DIRS") {
+            dirs.extend(
+                list.split(':')
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from),
+            );
+        }
+        dirs.extend(DIRS.iter().map(PathBuf::from));
+
+        for dir in dirs {
+            // Entries are kept in a directory named after their first letter, or its code in
+            // hexadecimal on case insensitive file systems.
+            for subdir in [first.to_string(), format!("{:x}", first as u32)].iter() {
+                match fs::read(dir.join(subdir).join(term)) {
+                    Ok(data) => return Terminfo::from_bytes(&data),
+                    Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no terminfo entry for {}", term),
+        ))
+    }
+
+    /// Parse a compiled terminfo entry.
+    pub fn from_bytes(data: &[u8]) -> io::Result<Terminfo> {
+        let invalid = || Error::new(ErrorKind::InvalidData, "invalid terminfo entry");
+        let read_u16 = |pos: usize| -> io::Result<u16> {
+            data.get(pos..pos + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or_else(invalid)
+        };
+
+        let number_size = match read_u16(0)? {
+            MAGIC => 2,
+            MAGIC_32BIT => 4,
+            _ => return Err(invalid()),
+        };
+        let names_size = read_u16(2)? as usize;
+        let bools_count = read_u16(4)? as usize;
+        let numbers_count = read_u16(6)? as usize;
+        let strings_count = read_u16(8)? as usize;
+        let table_size = read_u16(10)? as usize;
+
+        let names_start = 12;
+        let bools_start = names_start + names_size;
+        // Numbers are aligned on an even byte.
+        let numbers_start = (bools_start + bools_count + 1) & !1;
+        let strings_start = numbers_start + numbers_count * number_size;
+        let table_start = strings_start + strings_count * 2;
+        let table = data
+            .get(table_start..table_start + table_size)
+            .ok_or_else(invalid)?;
+
+        let names = &data[names_start..bools_start];
+        let names = names.split(|&b| b == 0).next().unwrap_or_default();
+        let names = String::from_utf8_lossy(names)
+            .split('|')
+            .map(String::from)
+            .collect();
+
+        let string = |index: usize| -> io::Result<Option<Vec<u8>>> {
+            if index >= strings_count {
+                return Ok(None);
+            }
+            // Absent and cancelled capabilities have negative offsets.
+            let offset = read_u16(strings_start + index * 2)? as i16;
+            if offset < 0 {
+                return Ok(None);
+            }
+            let s = table.get(offset as usize..).ok_or_else(invalid)?;
+            let len = s.iter().position(|&b| b == 0).ok_or_else(invalid)?;
+            Ok(Some(s[..len].to_vec()))
+        };
+
+        let function_keys = FUNCTION_KEYS
+            .iter()
+            .cloned()
+            .chain(FUNCTION_KEYS_FROM_11..FUNCTION_KEYS_FROM_11 + 53)
+            .zip(0..)
+            .map(|(index, n)| (index, function_key(n)));
+        let mut keys = Vec::new();
+        for (index, key) in KEYS.iter().cloned().chain(function_keys) {
+            match string(index)? {
+                // Single bytes are only sequences for Backspace: a Down key sending a newline
+                // would turn Enter into Down.
+                Some(ref seq) if seq.len() == 1 && key != Key::Backspace => {}
+                Some(ref seq) if seq.is_empty() => {}
+                // Modified F3 keys, such as ESC [ 1 ; 2 R, are also cursor position reports.
+                Some(ref seq) if seq.starts_with(b"\x1B[") && seq.ends_with(b"R") => {}
+                Some(seq) => keys.push((seq, key)),
+                None => {}
+            }
+        }
+
+        Ok(Terminfo { names, keys })
+    }
+
+    /// The names of the terminal, starting with its primary name.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The key sent as `seq`.
+    pub fn key(&self, seq: &[u8]) -> Option<Key> {
+        self.keys
+            .iter()
+            .find(|(s, _)| s[..] == *seq)
+            .map(|&(_, key)| key)
+    }
+
+    /// Whether `seq` starts a longer key sequence.
+    pub(crate) fn is_prefix(&self, seq: &[u8]) -> bool {
+        self.keys
+            .iter()
+            .any(|(s, _)| s.len() > seq.len() && s.starts_with(seq))
+    }
+}
+
+/// The key of the `n`th function key capability, counting kf1 as 0.
+///
+/// Past kf12, the capabilities are F1 to F12 again with the next modifiers every 12 keys.
+fn function_key(n: usize) -> Key {
+    let modifiers = match n / 12 {
+        0 => Modifiers::empty(),
+        1 => Modifiers::SHIFT,
+        2 => Modifiers::CTRL,
+        3 => Modifiers::CTRL | Modifiers::SHIFT,
+        4 => Modifiers::ALT,
+        _ => Modifiers::ALT | Modifiers::SHIFT,
+    };
+    Key::new(KeyCode::F((n % 12) as u8 + 1), modifiers)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::{Event, ParseOptions, Response};
+    use input::{InputParser, TermRead, TermReadEventsAndRaw};
+
+    /// Compile an entry with the given string capabilities.
+    fn compile(names: &str, strings: &[(usize, &str)], magic: u16) -> Vec<u8> {
+        let count = strings
+            .iter()
+            .map(|&(index, _)| index + 1)
+            .max()
+            .unwrap_or(0);
+        let mut offsets = vec![-1i16; count];
+        let mut table = Vec::new();
+        for &(index, s) in strings {
+            offsets[index] = table.len() as i16;
+            table.extend_from_slice(s.as_bytes());
+            table.push(0);
+        }
+
+        let mut data = Vec::new();
+        let header = [
+            magic,
+            names.len() as u16 + 1,
+            1,
+            1,
+            count as u16,
+            table.len() as u16,
+        ];
+        for n in header.iter() {
+            data.extend_from_slice(&n.to_le_bytes());
+        }
+        data.extend_from_slice(names.as_bytes());
+        // The names' NUL, a boolean, and the alignment byte.
+        data.extend_from_slice(&[0, 1]);
+        if data.len() % 2 != 0 {
+            data.push(0);
+        }
+        let number_size = if magic == MAGIC { 2 } else { 4 };
+        data.extend_from_slice(&[0xFF; 4][..number_size]);
+        for offset in offsets {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(&table);
+        data
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let strings = [
+            (55, "\x08"),
+            (59, "\x1B[P"),
+            (61, "\n"),
+            (66, "\x1BOP"),
+            (67, "\x1B[21~"),
+            (87, "\x1BOA"),
+            (201, "\x1B[1;2D"),
+            (216, "\x1B[23~"),
+            (218, "\x1B[1;2P"),
+            (220, "\x1B[1;2R"),
+            (230, "\x1B[1;5P"),
+            (267, "\x1B[1;4Q"),
+        ];
+        for &magic in [MAGIC, MAGIC_32BIT].iter() {
+            let terminfo =
+                Terminfo::from_bytes(&compile("st|simpleterm", &strings, magic)).unwrap();
+            assert_eq!(terminfo.names(), ["st", "simpleterm"]);
+            assert_eq!(terminfo.key(b"\x08"), Some(Key::Backspace));
+            assert_eq!(terminfo.key(b"\x1B[P"), Some(Key::Delete));
+            assert_eq!(terminfo.key(b"\n"), None);
+            assert_eq!(terminfo.key(b"\x1BOP"), Some(Key::F(1)));
+            assert_eq!(terminfo.key(b"\x1B[21~"), Some(Key::F(10)));
+            assert_eq!(terminfo.key(b"\x1B[23~"), Some(Key::F(11)));
+            // kf13 and up are modified function keys.
+            assert_eq!(
+                terminfo.key(b"\x1B[1;2P"),
+                Some(Key::Modified(KeyCode::F(1), Modifiers::SHIFT))
+            );
+            assert_eq!(
+                terminfo.key(b"\x1B[1;5P"),
+                Some(Key::Modified(KeyCode::F(1), Modifiers::CTRL))
+            );
+            assert_eq!(
+                terminfo.key(b"\x1B[1;4Q"),
+                Some(Key::Modified(
+                    KeyCode::F(2),
+                    Modifiers::ALT | Modifiers::SHIFT
+                ))
+            );
+            // Sequences that are cursor position reports as well are left out.
+            assert_eq!(terminfo.key(b"\x1B[1;2R"), None);
+            assert_eq!(terminfo.key(b"\x1BOA"), Some(Key::Up));
+            assert_eq!(
+                terminfo.key(b"\x1B[1;2D"),
+                Some(Key::Modified(KeyCode::Left, Modifiers::SHIFT))
+            );
+            assert!(terminfo.is_prefix(b"\x1BO"));
+            assert!(!terminfo.is_prefix(b"\x1BOA"));
+        }
+
+        assert!(Terminfo::from_bytes(b"").is_err());
+        let mut data = compile("st", &strings, MAGIC);
+        data.truncate(data.len() - 1);
+        assert!(Terminfo::from_bytes(&data).is_err());
+        data[0] = 0;
+        assert!(Terminfo::from_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let strings = [
+            (55, "\x08"),
+            (59, "\x1B[P"),
+            (66, "\x1B[11~"),
+            (87, "\x1BOA"),
+            (220, "\x1B[1;2R"),
+        ];
+        let terminfo = Terminfo::from_bytes(&compile("st", &strings, MAGIC)).unwrap();
+
+        let input = b"\x08\x1B[P\x1B[11~\x1B[1;5A\x1BOB\x1Ba\x1BOAx";
+        let events: Vec<Event> = input
+            .events()
+            .options(ParseOptions::default().terminfo(Some(terminfo.clone())))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                Event::Key(Key::Backspace),
+                Event::Key(Key::Delete),
+                Event::Key(Key::F(1)),
+                Event::Key(Key::Modified(KeyCode::Up, Modifiers::CTRL)),
+                Event::Key(Key::Down),
+                Event::Key(Key::Alt('a')),
+                Event::Key(Key::Up),
+                Event::Key(Key::Char('x')),
+            ]
+        );
+
+        // Cursor position reports are never taken for keys.
+        let mut events = b"\x1B[1;2R"
+            .events()
+            .options(ParseOptions::default().terminfo(Some(terminfo.clone())));
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            Event::Response(Response::CursorPosition(2, 1))
+        );
+
+        // Without the entry, the built-in sequences are used.
+        let events: Vec<Event> = input.events().map(Result::unwrap).collect();
+        assert_eq!(events[0], Event::Key(Key::Ctrl('h')));
+        assert_eq!(events[1], Event::Key(Key::F(1)));
+
+        // Partial sequences wait for the rest of their bytes.
+        let mut parser =
+            InputParser::new().options(ParseOptions::default().terminfo(Some(terminfo)));
+        parser.push(b"\x1B[1");
+        assert_eq!(parser.next(), None);
+        parser.push(b"1~");
+        assert_eq!(
+            parser.next(),
+            Some((Event::Key(Key::F(1)), b"\x1B[11~".to_vec()))
+        );
+
+        // Bytes read ahead for a sequence that did not complete are not lost.
+        let strings = [(164, "\x1BOF1")];
+        let kend = Terminfo::from_bytes(&compile("st", &strings, MAGIC)).unwrap();
+        let events: Vec<(Event, Vec<u8>)> = b"\x1BOF1\x1BOFx\x1BOF"
+            .events_and_raw()
+            .options(ParseOptions::default().terminfo(Some(kend.clone())))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (Event::Key(Key::End), b"\x1BOF1".to_vec()),
+                (Event::Key(Key::End), b"\x1BOF".to_vec()),
+                (Event::Key(Key::Char('x')), b"x".to_vec()),
+                (Event::Key(Key::End), b"\x1BOF".to_vec()),
+            ]
+        );
+        let mut parser = InputParser::new().options(ParseOptions::default().terminfo(Some(kend)));
+        parser.push(b"\x1BOFx");
+        let events: Vec<Event> = parser.map(|(event, _)| event).collect();
+        assert_eq!(events, [Event::Key(Key::End), Event::Key(Key::Char('x'))]);
+    }
+
+    #[test]
+    fn test_load() {
+        assert_eq!(
+            Terminfo::load("../x").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            Terminfo::load("").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+}